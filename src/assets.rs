@@ -0,0 +1,166 @@
+//! Finding the files of the game. Resource directories are searched in this order:
+//!
+//! 1. the directory given on the command line
+//! 2. `$AMAZING_GRAME_RESOURCES`
+//! 3. `resources` next to the executable, or two levels up for builds in `target/<profile>`
+//! 4. `resources` in the source directory the game was built from
+//! 5. `amazing_grame` in `$XDG_DATA_HOME` (or `~/.local/share`) and `$XDG_DATA_DIRS`
+//!
+//! An asset pack is a directory `packs/<name>` inside a resource directory. Its files replace the
+//! files of the same name, e.g. `FiraMono-Bold.ttf`, `sprites.txt` with its images or `sounds/shot.wav`.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+pub const RESOURCES_ENV: &'static str = "AMAZING_GRAME_RESOURCES";
+const DATA_DIRECTORY: &'static str = "amazing_grame";
+const PACKS_DIRECTORY: &'static str = "packs";
+
+/// The directories files are looked up in, the ones of an asset pack first
+#[derive(Clone, Debug)]
+pub struct Assets {
+    directories: Vec<PathBuf>
+}
+
+impl Assets {
+    /// Find the existing resource directories, and the asset pack `pack` in them if given
+    pub fn locate(resource_directory: Option<&Path>, pack: Option<&str>) -> Result<Assets, String> {
+        let candidates = search_path(resource_directory);
+        let existing: Vec<PathBuf> = candidates.iter().filter(|d| d.is_dir()).cloned().collect();
+        if existing.is_empty() {
+            return Err(format!("Could not find the resources of the game. Searched:\n{}\n\
+                                Use --resources DIRECTORY or set {} to their location.",
+                               list(&candidates), RESOURCES_ENV));
+        }
+        let mut directories = vec![];
+        if let Some(pack) = pack {
+            directories.extend(existing.iter().map(|d| d.join(PACKS_DIRECTORY).join(pack)).filter(|d| d.is_dir()));
+            if directories.is_empty() {
+                return Err(format!("Could not find the asset pack {}. Searched:\n{}",
+                                   pack, list(&existing.iter().map(|d| d.join(PACKS_DIRECTORY).join(pack)).collect::<Vec<_>>())));
+            }
+        }
+        directories.extend(existing);
+        Ok(Assets { directories: directories })
+    }
+
+    /// The path of `file`, relative to the resource directories, in the first directory that has it
+    pub fn find(&self, file: &str) -> Option<PathBuf> {
+        self.directories.iter().map(|d| d.join(file)).find(|path| path.is_file())
+    }
+
+    /// Like `find`, but with an error naming the searched directories
+    pub fn require(&self, file: &str) -> Result<PathBuf, String> {
+        self.find(file).ok_or_else(|| format!("Could not find {}. Searched:\n{}", file, list(&self.directories)))
+    }
+
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+}
+
+/// All directories which may contain the resources, in the order they are searched
+pub fn search_path(resource_directory: Option<&Path>) -> Vec<PathBuf> {
+    let mut path = vec![];
+    if let Some(directory) = resource_directory {
+        path.push(directory.to_owned());
+    }
+    if let Some(directory) = env::var_os(RESOURCES_ENV) {
+        path.push(PathBuf::from(directory));
+    }
+    if let Some(exe_directory) = env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.to_owned())) {
+        path.push(exe_directory.join("resources"));
+        path.push(exe_directory.join("../../resources"));
+    }
+    path.push(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources"));
+    let data_home = env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    if let Some(data_home) = data_home {
+        path.push(data_home.join(DATA_DIRECTORY));
+    }
+    let data_dirs = env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    for directory in data_dirs.split(':').filter(|d| !d.is_empty()) {
+        path.push(Path::new(directory).join(DATA_DIRECTORY));
+    }
+    path
+}
+
+fn list(directories: &[PathBuf]) -> String {
+    directories.iter().map(|d| format!("  {}", d.display())).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process;
+
+    use super::*;
+
+    /// An empty directory for one test, removed again when dropped
+    struct TempDirectory(PathBuf);
+
+    impl TempDirectory {
+        fn new(name: &str) -> TempDirectory {
+            let path = env::temp_dir().join(format!("amazing_grame_{}_{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDirectory(path)
+        }
+
+        fn add(&self, file: &str) -> PathBuf {
+            let path = self.0.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn given_directories_are_searched_first() {
+        let given = TempDirectory::new("given");
+        let from_env = TempDirectory::new("env");
+        env::set_var(RESOURCES_ENV, &from_env.0);
+        let path = search_path(Some(&given.0));
+        env::remove_var(RESOURCES_ENV);
+        assert_eq!(path[..2], [given.0.clone(), from_env.0.clone()]);
+        let source = path.iter().position(|d| *d == Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")).unwrap();
+        let data = path.iter().position(|d| d.ends_with(DATA_DIRECTORY)).unwrap();
+        assert!(source < data);
+        // The source directory has the resources, after the given one
+        given.add("sprites.txt");
+        let assets = Assets::locate(Some(&given.0), None).unwrap();
+        assert_eq!(assets.directories()[0], given.0);
+        assert_eq!(assets.find("sprites.txt"), Some(given.0.join("sprites.txt")));
+        assert_eq!(assets.find("FiraMono-Bold.ttf"), Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/FiraMono-Bold.ttf")));
+    }
+
+    #[test]
+    fn packs_replace_files_of_the_same_name() {
+        let resources = TempDirectory::new("packs");
+        resources.add("sprites.txt");
+        resources.add("sounds/shot.wav");
+        let packed = resources.add("packs/retro/sounds/shot.wav");
+        let assets = Assets::locate(Some(&resources.0), Some("retro")).unwrap();
+        assert_eq!(assets.find("sounds/shot.wav"), Some(packed));
+        assert_eq!(assets.find("sprites.txt"), Some(resources.0.join("sprites.txt")));
+        let without_pack = Assets::locate(Some(&resources.0), None).unwrap();
+        assert_eq!(without_pack.find("sounds/shot.wav"), Some(resources.0.join("sounds/shot.wav")));
+        assert!(Assets::locate(Some(&resources.0), Some("modern")).is_err());
+    }
+
+    #[test]
+    fn missing_assets_name_the_searched_directories() {
+        let resources = TempDirectory::new("missing");
+        let assets = Assets::locate(Some(&resources.0), None).unwrap();
+        assert_eq!(assets.find("nothing.png"), None);
+        let error = assets.require("nothing.png").unwrap_err();
+        assert!(error.contains("nothing.png"), "{}", error);
+        assert!(error.contains(&resources.0.display().to_string()), "{}", error);
+    }
+}
@@ -0,0 +1,243 @@
+//! Sound effects triggered by game events. The sounds are synthesized unless the resources contain
+//! `sounds/<name>.wav`, and are played by a backend which does nothing if there is no audio device or
+//! the `audio` feature is off.
+
+#[cfg(feature = "audio")]
+pub mod rodio_backend;
+pub mod synth;
+pub mod wav;
+
+use std::collections::HashMap;
+
+use assets::Assets;
+use game::event::GameEvent;
+
+pub const DEFAULT_VOLUME: f64 = 0.5;
+pub const VOLUME_STEP: f64 = 0.1;
+
+/// Number of ticks between two thrust sounds of the same ship while its engine keeps running
+const THRUST_REPEAT_TICKS: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    Shot,
+    Tether,
+    StarDelivered,
+    Swallowed,
+    Thrust
+}
+
+impl Sound {
+    pub fn all() -> [Sound; 5] {
+        [Sound::Shot, Sound::Tether, Sound::StarDelivered, Sound::Swallowed, Sound::Thrust]
+    }
+
+    /// Name of the file replacing the synthesized sound, without the extension
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Shot => "shot",
+            Sound::Tether => "tether",
+            Sound::StarDelivered => "star_delivered",
+            Sound::Swallowed => "swallowed",
+            Sound::Thrust => "thrust"
+        }
+    }
+
+    /// Loudness relative to the other sounds
+    fn gain(self) -> f64 {
+        match self {
+            Sound::Shot => 0.5,
+            Sound::Tether => 0.6,
+            Sound::StarDelivered => 0.8,
+            Sound::Swallowed => 1.0,
+            Sound::Thrust => 0.2
+        }
+    }
+}
+
+/// Mono samples between -1 and 1
+#[derive(Clone, Debug)]
+pub struct SoundData {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>
+}
+
+/// The samples of all sounds, from the resources if they have them and synthesized otherwise
+pub fn load_sounds(assets: &Assets) -> HashMap<Sound, SoundData> {
+    Sound::all().iter().map(|&sound| {
+        let file = assets.find(&format!("sounds/{}.wav", sound.name()))
+            .and_then(|path| wav::load(&path).map_err(|error| eprintln!("{}", error)).ok());
+        let data = file.unwrap_or_else(|| SoundData { sample_rate: synth::SAMPLE_RATE, samples: synth::samples(sound) });
+        (sound, data)
+    }).collect()
+}
+
+pub trait AudioBackend {
+    /// Start playing `sound` at `volume` between 0 and 1, mixed with everything already playing
+    fn play(&mut self, sound: Sound, volume: f64);
+}
+
+/// Plays nothing, for headless runs and machines without audio
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, _sound: Sound, _volume: f64) {}
+}
+
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    /// Master volume between 0 and 1
+    pub volume: f64,
+    /// Silences all sounds without changing the volume
+    pub muted: bool,
+    /// Ticks until each ship's engine may be heard again
+    thrust_cooldown: Vec<u32>
+}
+
+impl Audio {
+    pub fn new(backend: Box<dyn AudioBackend>) -> Audio {
+        Audio {
+            backend: backend,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            thrust_cooldown: vec![]
+        }
+    }
+
+    /// Audio with the best available backend
+    pub fn open(assets: &Assets) -> Audio {
+        Audio::new(default_backend(assets))
+    }
+
+    /// Audio which never makes a sound
+    pub fn silent() -> Audio {
+        Audio::new(Box::new(NullBackend))
+    }
+
+    /// Play the sounds of the events of the last tick
+    pub fn handle_events(&mut self, events: &[GameEvent]) {
+        for cooldown in self.thrust_cooldown.iter_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+        for event in events.iter() {
+            match *event {
+                GameEvent::Shot { .. } => self.play(Sound::Shot),
+                GameEvent::TetherCreated { .. } => self.play(Sound::Tether),
+                GameEvent::StarDelivered { .. } => self.play(Sound::StarDelivered),
+                GameEvent::ShipSwallowed { .. } => self.play(Sound::Swallowed),
+                GameEvent::Thrust { ship, .. } => {
+                    if self.thrust_cooldown.len() <= ship {
+                        self.thrust_cooldown.resize(ship + 1, 0);
+                    }
+                    if self.thrust_cooldown[ship] == 0 {
+                        self.thrust_cooldown[ship] = THRUST_REPEAT_TICKS;
+                        self.play(Sound::Thrust);
+                    }
+                }
+                GameEvent::Collision { .. } | GameEvent::ShipReset { .. } => {}
+            }
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if !self.muted && self.volume > 0.0 {
+            self.backend.play(sound, self.volume * sound.gain());
+        }
+    }
+
+    /// Change the volume by `step`, keeping it between 0 and 1
+    pub fn change_volume(&mut self, step: f64) {
+        self.volume = (self.volume + step).clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(feature = "audio")]
+fn default_backend(assets: &Assets) -> Box<dyn AudioBackend> {
+    match rodio_backend::RodioBackend::new(load_sounds(assets)) {
+        Some(backend) => Box::new(backend),
+        None => {
+            eprintln!("No audio device found, playing without sound");
+            Box::new(NullBackend)
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn default_backend(_assets: &Assets) -> Box<dyn AudioBackend> {
+    Box::new(NullBackend)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use ::point::Point;
+
+    /// Remembers every sound it is asked to play
+    struct RecordingBackend {
+        played: Rc<RefCell<Vec<(Sound, f64)>>>
+    }
+
+    impl AudioBackend for RecordingBackend {
+        fn play(&mut self, sound: Sound, volume: f64) {
+            self.played.borrow_mut().push((sound, volume));
+        }
+    }
+
+    fn recording_audio() -> (Audio, Rc<RefCell<Vec<(Sound, f64)>>>) {
+        let played = Rc::new(RefCell::new(vec![]));
+        (Audio::new(Box::new(RecordingBackend { played: played.clone() })), played)
+    }
+
+    fn shot() -> GameEvent {
+        GameEvent::Shot { ship: 0, pos: Point{x: 0.0, y: 0.0} }
+    }
+
+    fn thrust(ship: usize) -> GameEvent {
+        GameEvent::Thrust { ship: ship, pos: Point{x: 0.0, y: 0.0}, direction: Point{x: 1.0, y: 0.0} }
+    }
+
+    #[test]
+    fn events_play_their_sounds_at_the_volume() {
+        let (mut audio, played) = recording_audio();
+        audio.handle_events(&[shot(), GameEvent::Collision { pos: Point{x: 0.0, y: 0.0}, speed: 500.0 }]);
+        assert_eq!(*played.borrow(), vec![(Sound::Shot, DEFAULT_VOLUME * Sound::Shot.gain())]);
+    }
+
+    #[test]
+    fn muting_silences_everything_until_unmuted() {
+        let (mut audio, played) = recording_audio();
+        audio.muted = true;
+        audio.handle_events(&[shot(), thrust(0)]);
+        assert!(played.borrow().is_empty());
+        audio.muted = false;
+        audio.handle_events(&[shot()]);
+        assert_eq!(played.borrow().len(), 1);
+    }
+
+    #[test]
+    fn volume_stays_between_zero_and_one() {
+        let (mut audio, played) = recording_audio();
+        for _ in 0..20 {
+            audio.change_volume(VOLUME_STEP);
+        }
+        assert_eq!(audio.volume, 1.0);
+        audio.change_volume(-5.0);
+        assert_eq!(audio.volume, 0.0);
+        audio.handle_events(&[shot()]);
+        assert!(played.borrow().is_empty());
+    }
+
+    #[test]
+    fn running_engines_are_heard_every_few_ticks() {
+        let (mut audio, played) = recording_audio();
+        for _ in 0..2 * THRUST_REPEAT_TICKS {
+            audio.handle_events(&[thrust(0), thrust(1)]);
+        }
+        // Once per ship right away, and again when the cooldown ran out
+        assert_eq!(played.borrow().len(), 4);
+        assert!(played.borrow().iter().all(|&(sound, _)| sound == Sound::Thrust));
+    }
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use rodio::{self, Endpoint, Sink};
+use rodio::buffer::SamplesBuffer;
+
+use audio::{AudioBackend, Sound, SoundData};
+
+/// Plays sounds on the default audio device
+pub struct RodioBackend {
+    endpoint: Endpoint,
+    sounds: HashMap<Sound, SoundData>
+}
+
+impl RodioBackend {
+    /// `None` if there is no audio device
+    pub fn new(sounds: HashMap<Sound, SoundData>) -> Option<RodioBackend> {
+        rodio::get_default_endpoint().map(|endpoint| {
+            RodioBackend {
+                endpoint: endpoint,
+                sounds: sounds
+            }
+        })
+    }
+}
+
+impl AudioBackend for RodioBackend {
+    fn play(&mut self, sound: Sound, volume: f64) {
+        let mut sink = Sink::new(&self.endpoint);
+        sink.set_volume(volume as f32);
+        let data = &self.sounds[&sound];
+        sink.append(SamplesBuffer::new(1, data.sample_rate, data.samples.clone()));
+        // Keep playing after the sink is dropped
+        sink.detach();
+    }
+}
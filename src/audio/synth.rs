@@ -0,0 +1,61 @@
+//! Generation of the sound effects as mono samples between -1 and 1
+
+use std::f64::consts::PI;
+
+use audio::Sound;
+
+pub const SAMPLE_RATE: u32 = 22050;
+
+pub fn samples(sound: Sound) -> Vec<f32> {
+    match sound {
+        Sound::Shot => sweep(900.0, 300.0, 0.12, 30.0),
+        Sound::Tether => sweep(400.0, 800.0, 0.15, 15.0),
+        Sound::StarDelivered => {
+            let mut samples = vec![];
+            for &frequency in [523.25, 659.25, 783.99].iter() {
+                samples.extend(sweep(frequency, frequency, 0.08, 20.0));
+            }
+            samples
+        }
+        Sound::Swallowed => mix(&sweep(200.0, 40.0, 0.8, 4.0), &noise(0.8, 5.0, 0.05), 0.5),
+        Sound::Thrust => noise(0.1, 0.0, 0.1)
+    }
+}
+
+/// A sine tone gliding from `start` to `end` Hz, fading out with rate `decay`
+fn sweep(start: f64, end: f64, duration: f64, decay: f64) -> Vec<f32> {
+    let count = (duration * SAMPLE_RATE as f64) as usize;
+    let mut phase = 0.0;
+    (0..count).map(|i| {
+        let t = i as f64 / SAMPLE_RATE as f64;
+        let frequency = start + (end - start) * t / duration;
+        phase += 2.0 * PI * frequency / SAMPLE_RATE as f64;
+        (phase.sin() * (-decay * t).exp()) as f32
+    }).collect()
+}
+
+/// White noise smoothed by a low pass filter, where a smaller `smoothing` means a deeper sound
+fn noise(duration: f64, decay: f64, smoothing: f64) -> Vec<f32> {
+    let count = (duration * SAMPLE_RATE as f64) as usize;
+    let mut state: u32 = 0x9e3779b9;
+    let mut filtered = 0.0;
+    (0..count).map(|i| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let white = state as f64 / u32::MAX as f64 * 2.0 - 1.0;
+        filtered += (white - filtered) * smoothing;
+        let t = i as f64 / SAMPLE_RATE as f64;
+        // Fade in and out to avoid clicks
+        let envelope = (t / 0.01).min(1.0) * ((duration - t) / 0.01).min(1.0) * (-decay * t).exp();
+        (filtered * 4.0 * envelope).clamp(-1.0, 1.0) as f32
+    }).collect()
+}
+
+fn mix(first: &[f32], second: &[f32], balance: f32) -> Vec<f32> {
+    (0..first.len().max(second.len())).map(|i| {
+        let a = first.get(i).cloned().unwrap_or(0.0);
+        let b = second.get(i).cloned().unwrap_or(0.0);
+        a * (1.0 - balance) + b * balance
+    }).collect()
+}
@@ -0,0 +1,61 @@
+//! Reading of uncompressed WAV files, mixed down to mono
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use audio::SoundData;
+
+pub fn load(path: &Path) -> Result<SoundData, String> {
+    let mut data = vec![];
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn parse(data: &[u8]) -> Result<SoundData, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("Not a WAV file".to_string());
+    }
+    let mut format = None;
+    let mut position = 12;
+    while position + 8 <= data.len() {
+        let id = &data[position..position + 4];
+        let size = u32_at(data, position + 4) as usize;
+        let body = &data[position + 8..(position + 8 + size).min(data.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                // Format tag, channels, sample rate and bits per sample
+                format = Some((u16_at(body, 0), u16_at(body, 2), u32_at(body, 4), u16_at(body, 14)));
+            }
+            b"data" => {
+                let (tag, channels, sample_rate, bits) = format.ok_or("Sample data before the format".to_string())?;
+                if channels == 0 {
+                    return Err("No channels".to_string());
+                }
+                let samples: Vec<f32> = match (tag, bits) {
+                    (1, 8) => body.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+                    (1, 16) => body.chunks(2).filter(|c| c.len() == 2).map(|c| u16_at(c, 0) as i16 as f32 / 32768.0).collect(),
+                    (3, 32) => body.chunks(4).filter(|c| c.len() == 4).map(|c| f32::from_bits(u32_at(c, 0))).collect(),
+                    _ => return Err(format!("Unsupported format {} with {} bits per sample", tag, bits))
+                };
+                let channels = channels as usize;
+                let mono = samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+                return Ok(SoundData { sample_rate: sample_rate, samples: mono });
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        position += 8 + size + size % 2;
+    }
+    Err("No sample data".to_string())
+}
+
+fn u16_at(data: &[u8], position: usize) -> u16 {
+    data[position] as u16 | (data[position + 1] as u16) << 8
+}
+
+fn u32_at(data: &[u8], position: usize) -> u32 {
+    u16_at(data, position) as u32 | (u16_at(data, position + 2) as u32) << 16
+}
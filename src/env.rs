@@ -0,0 +1,244 @@
+//! Headless, Gym-style interface to the game for training agents.
+//!
+//! ```ignore
+//! let mut env = Environment::new(EnvConfig::default());
+//! let mut observations = env.reset(42);
+//! loop {
+//!     let actions = observations.iter().map(|o| policy(o)).collect();
+//!     let (next, rewards, done) = env.step(actions);
+//!     observations = next;
+//!     if done { break; }
+//! }
+//! ```
+
+use std::cmp::Ordering;
+
+use ::point::Point;
+use game::{Game, ARENA_SIZE};
+use game::input::Actions;
+use game::object::ObjectType;
+
+const POSITION_SCALE: f64 = 1000.0;
+const VELOCITY_SCALE: f64 = 1000.0;
+const ANGULAR_VELOCITY_SCALE: f64 = 5.0;
+
+const SHIP_FEATURES: usize = 8;
+const BODY_FEATURES: usize = 11;
+const SPRING_FEATURES: usize = 3;
+
+/// The observation of a single player, a flat vector of features
+pub type Observation = Vec<f64>;
+
+/// Selects which parts of the game state end up in an `Observation`
+#[derive(Clone, Debug)]
+pub struct ObservationConfig {
+    /// Position, velocity, heading and spawn protection of the own ship
+    pub include_ship: bool,
+    /// Number of closest objects whose relative state is included, padded with zeros
+    pub num_nearby_bodies: usize,
+    /// Which objects are tethered to the own ship
+    pub include_springs: bool
+}
+
+impl Default for ObservationConfig {
+    fn default() -> ObservationConfig {
+        ObservationConfig {
+            include_ship: true,
+            num_nearby_bodies: 8,
+            include_springs: true
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub arena_size: Point,
+    /// The episode ends after this many game ticks even if stars are left
+    pub max_steps: usize,
+    /// Number of game ticks simulated per call to `step`, using the same actions
+    pub frame_skip: usize,
+    pub observation: ObservationConfig
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            arena_size: ARENA_SIZE,
+            max_steps: 10000,
+            frame_skip: 1,
+            observation: ObservationConfig::default()
+        }
+    }
+}
+
+/// Wraps a `Game` without any rendering or input handling, so it can be stepped as fast as possible.
+pub struct Environment {
+    pub config: EnvConfig,
+    game: Game,
+    steps: usize
+}
+
+impl Environment {
+    pub fn new(config: EnvConfig) -> Environment {
+        let game = Game::with_seed(config.arena_size, 0);
+        Environment {
+            config: config,
+            game: game,
+            steps: 0
+        }
+    }
+
+    /// Start a new episode. The same seed always results in the same initial state.
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.game = Game::with_seed(self.config.arena_size, seed);
+        self.steps = 0;
+        self.observations()
+    }
+
+    /// Advance the game by `frame_skip` ticks. `actions` needs one entry per player.
+    /// Returns the new observations, the change of each player's score and whether the episode is over.
+    pub fn step(&mut self, actions: Vec<Actions>) -> (Vec<Observation>, Vec<f64>, bool) {
+        let old_score = self.game.score.clone();
+        for _ in 0..self.config.frame_skip.max(1) {
+            if self.is_done() {
+                break;
+            }
+            self.game.control(actions.clone());
+            self.game.timestep();
+            self.steps += 1;
+        }
+        let rewards = self.game.score.iter().zip(old_score.iter())
+            .map(|(new, old)| (new - old) as f64)
+            .collect();
+        (self.observations(), rewards, self.is_done())
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.game.score.len()
+    }
+
+    /// Length of each `Observation` with the current configuration
+    pub fn observation_size(&self) -> usize {
+        let config = &self.config.observation;
+        let mut size = 0;
+        if config.include_ship {
+            size += SHIP_FEATURES;
+        }
+        let body_features = if config.include_springs { BODY_FEATURES + 1 } else { BODY_FEATURES };
+        size += config.num_nearby_bodies * body_features;
+        if config.include_springs {
+            size += SPRING_FEATURES;
+        }
+        size
+    }
+
+    fn is_done(&self) -> bool {
+        self.game.game_over || self.steps >= self.config.max_steps
+    }
+
+    fn observations(&self) -> Vec<Observation> {
+        (0..self.num_players()).map(|ship_num| self.observe(ship_num)).collect()
+    }
+
+    fn observe(&self, ship_num: usize) -> Observation {
+        let config = &self.config.observation;
+        let game = &self.game;
+        let ship_id = game.get_ship(ship_num).body;
+        let ship = game.sim.get_body(ship_id);
+        let tethered = |body: usize| game.springs.iter().any(|s| s.body1 == body && s.body2 == ship_id);
+        let mut observation = Vec::with_capacity(self.observation_size());
+        if config.include_ship {
+            observation.push(ship.pos.x / game.arena_size.x);
+            observation.push(ship.pos.y / game.arena_size.y);
+            observation.push(ship.vel.x / VELOCITY_SCALE);
+            observation.push(ship.vel.y / VELOCITY_SCALE);
+            observation.push(ship.apos.cos());
+            observation.push(ship.apos.sin());
+            observation.push(ship.avel / ANGULAR_VELOCITY_SCALE);
+            observation.push(if game.is_spawn_protected(ship_num) { 1.0 } else { 0.0 });
+        }
+
+        let mut nearby: Vec<_> = game.objects.iter()
+            .filter(|o| o.body != ship_id)
+            .map(|o| (o, game.sim.get_body(o.body)))
+            .collect();
+        nearby.sort_by(|&(_, a), &(_, b)| {
+            (a.pos - ship.pos).norm().partial_cmp(&(b.pos - ship.pos).norm()).unwrap_or(Ordering::Equal)
+        });
+        for i in 0..config.num_nearby_bodies {
+            match nearby.get(i) {
+                Some(&(object, body)) => {
+                    let relative_pos = body.pos - ship.pos;
+                    let relative_vel = body.vel - ship.vel;
+                    observation.push(1.0);
+                    observation.push(relative_pos.x / POSITION_SCALE);
+                    observation.push(relative_pos.y / POSITION_SCALE);
+                    observation.push(relative_vel.x / VELOCITY_SCALE);
+                    observation.push(relative_vel.y / VELOCITY_SCALE);
+                    observation.push(body.radius / POSITION_SCALE);
+                    observation.push(one_hot(object.type_ == ObjectType::Star));
+                    observation.push(one_hot(object.type_ == ObjectType::BlackHole));
+                    observation.push(one_hot(object.type_ == ObjectType::Mothership));
+                    observation.push(one_hot(matches!(object.type_, ObjectType::Ship(_))));
+                    observation.push(one_hot(matches!(object.type_, ObjectType::Bullet(..))));
+                    if config.include_springs {
+                        observation.push(one_hot(tethered(object.body)));
+                    }
+                }
+                None => {
+                    let body_features = if config.include_springs { BODY_FEATURES + 1 } else { BODY_FEATURES };
+                    observation.extend((0..body_features).map(|_| 0.0));
+                }
+            }
+        }
+
+        if config.include_springs {
+            let mut counts = [0.0; SPRING_FEATURES];
+            for object in game.objects.iter().filter(|o| tethered(o.body)) {
+                match object.type_ {
+                    ObjectType::Star => counts[0] += 1.0,
+                    ObjectType::BlackHole => counts[1] += 1.0,
+                    _ => counts[2] += 1.0
+                }
+            }
+            observation.extend(counts.iter());
+        }
+        observation
+    }
+}
+
+fn one_hot(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_observations() {
+        let mut first = Environment::new(EnvConfig::default());
+        let mut second = Environment::new(EnvConfig::default());
+        assert_eq!(first.reset(42), second.reset(42));
+        for tick in 0..1000u32 {
+            let actions = vec![Actions::from_bits((tick / 20 % 32) as u8), Actions::from_bits((tick / 7 % 32) as u8)];
+            let (observations, rewards, done) = first.step(actions.clone());
+            assert_eq!((observations, rewards, done), second.step(actions));
+            if done {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn observations_have_the_configured_size() {
+        let mut env = Environment::new(EnvConfig::default());
+        for observation in env.reset(1) {
+            assert_eq!(observation.len(), env.observation_size());
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::f64::consts::PI;
+
+use ::point::Point;
+use game::Game;
+use game::input::Actions;
+use game::object::ObjectType;
+use simulation::body::Body;
+
+const SHOOT_RANGE: f64 = 600.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Difficulty {
+    Easy, Medium, Hard
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None
+        }
+    }
+
+    /// Number of ticks between two decisions of the bot
+    fn reaction_ticks(self) -> usize {
+        match self {
+            Difficulty::Easy => 20,
+            Difficulty::Medium => 8,
+            Difficulty::Hard => 1
+        }
+    }
+
+    /// Maximum angle (in radians) between heading and target at which the bot still shoots
+    fn aim_tolerance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.35,
+            Difficulty::Medium => 0.2,
+            Difficulty::Hard => 0.1
+        }
+    }
+
+    /// Distance to a black hole at which the bot starts steering away from it
+    fn danger_distance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 150.0,
+            Difficulty::Hard => 300.0
+        }
+    }
+
+    fn max_speed(self) -> f64 {
+        match self {
+            Difficulty::Easy => 400.0,
+            Difficulty::Medium => 600.0,
+            Difficulty::Hard => 800.0
+        }
+    }
+}
+
+/// A scripted opponent producing `Actions` for one player slot from the game state.
+pub struct Bot {
+    pub ship_num: usize,
+    pub difficulty: Difficulty,
+    ticks: usize,
+    actions: Actions
+}
+
+impl Bot {
+    pub fn new(ship_num: usize, difficulty: Difficulty) -> Bot {
+        Bot {
+            ship_num: ship_num,
+            difficulty: difficulty,
+            ticks: 0,
+            actions: Actions::default()
+        }
+    }
+
+    pub fn actions(&mut self, game: &Game) -> Actions {
+        if self.ticks % self.difficulty.reaction_ticks() == 0 {
+            self.actions = self.plan(game);
+        }
+        self.ticks += 1;
+        let actions = self.actions;
+        // Respawning is a single key press, not a held key
+        self.actions.respawn = false;
+        actions
+    }
+
+    fn plan(&self, game: &Game) -> Actions {
+        let mut actions = Actions::default();
+        let ship_id = game.get_ship(self.ship_num).body;
+        let ship = game.sim.get_body(ship_id);
+        let mut towing = false;
+        for spring in game.springs.iter().filter(|s| s.body2 == ship_id) {
+            match object_type(game, spring.body1) {
+                Some(ObjectType::BlackHole) => {
+                    // There is no escaping a black hole on a leash
+                    actions.respawn = true;
+                    return actions;
+                }
+                Some(ObjectType::Star) => towing = true,
+                _ => {}
+            }
+        }
+        let target = if towing {
+            Some(game.sim.get_body(game.get_mothership().body))
+        }
+        else {
+            self.nearest_free_star(game, ship, ship_id)
+        };
+        let target = match target {
+            Some(target) => target,
+            None => return actions
+        };
+        let to_target = target.pos - ship.pos;
+        let desired = to_target.normalized() + self.avoidance(game, ship);
+        let heading_error = angle_difference(desired.y.atan2(desired.x), ship.apos);
+        let tolerance = self.difficulty.aim_tolerance();
+        actions.rotate_right = heading_error > tolerance * 0.5;
+        actions.rotate_left = heading_error < -tolerance * 0.5;
+        actions.boost = heading_error.abs() < 2.0 * tolerance
+            && ship.vel * desired.normalized() < self.difficulty.max_speed();
+        if !towing && to_target.norm() < SHOOT_RANGE {
+            let aim_error = angle_difference(to_target.y.atan2(to_target.x), ship.apos);
+            actions.shoot = aim_error.abs() < tolerance && self.line_of_fire_is_clear(game, ship, target);
+        }
+        actions
+    }
+
+    /// The closest star which is not already tethered to our ship
+    fn nearest_free_star<'a>(&self, game: &'a Game, ship: &Body, ship_id: usize) -> Option<&'a Body> {
+        game.objects.iter()
+            .filter(|o| o.type_ == ObjectType::Star)
+            .filter(|o| !game.springs.iter().any(|s| s.body1 == o.body && s.body2 == ship_id))
+            .map(|o| game.sim.get_body(o.body))
+            .fold(None, |closest: Option<&Body>, star| {
+                match closest {
+                    Some(c) if (c.pos - ship.pos).norm() <= (star.pos - ship.pos).norm() => Some(c),
+                    _ => Some(star)
+                }
+            })
+    }
+
+    /// Steering correction pointing away from nearby black holes
+    fn avoidance(&self, game: &Game, ship: &Body) -> Point {
+        let danger_distance = self.difficulty.danger_distance();
+        let mut correction = Point{x: 0.0, y: 0.0};
+        if danger_distance <= 0.0 {
+            return correction;
+        }
+        for blackhole in game.objects.iter().filter(|o| o.type_ == ObjectType::BlackHole) {
+            let blackhole = game.sim.get_body(blackhole.body);
+            let away = ship.pos - blackhole.pos;
+            let distance = away.norm() - blackhole.radius - ship.radius;
+            if distance < danger_distance {
+                correction += away.normalized() * (2.0 * (danger_distance - distance) / danger_distance);
+            }
+        }
+        correction
+    }
+
+    /// Whether a bullet fired now would hit a black hole before reaching the target
+    fn line_of_fire_is_clear(&self, game: &Game, ship: &Body, target: &Body) -> bool {
+        if self.difficulty == Difficulty::Easy {
+            return true;
+        }
+        let direction = Point::from_angle(ship.apos);
+        let target_distance = (target.pos - ship.pos).norm();
+        !game.objects.iter().filter(|o| o.type_ == ObjectType::BlackHole).any(|o| {
+            let blackhole = game.sim.get_body(o.body);
+            let offset = blackhole.pos - ship.pos;
+            let along = offset * direction;
+            along > 0.0 && along < target_distance && offset.cross(direction).abs() < blackhole.radius
+        })
+    }
+}
+
+fn object_type(game: &Game, body: usize) -> Option<ObjectType> {
+    game.objects.iter().find(|o| o.body == body).map(|o| o.type_)
+}
+
+/// Signed difference between two angles, wrapped to [-pi, pi)
+fn angle_difference(target: f64, current: f64) -> f64 {
+    let difference = (target - current + PI) % (2.0 * PI);
+    if difference < 0.0 {
+        difference + PI
+    }
+    else {
+        difference - PI
+    }
+}
@@ -0,0 +1,20 @@
+use ::point::Point;
+
+/// Something noteworthy that happened during a tick, for effects and statistics
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    /// A ship flew into a black hole
+    ShipSwallowed { ship: usize, pos: Point },
+    /// A player reset their ship on purpose
+    ShipReset { ship: usize, pos: Point },
+    /// A ship fired its engine, `direction` is where the ship is heading
+    Thrust { ship: usize, pos: Point, direction: Point },
+    /// Two bodies touched, `speed` is their relative velocity
+    Collision { pos: Point, speed: f64 },
+    /// A star reached the mothership, `ship` is the player who towed it there, if any
+    StarDelivered { ship: Option<usize>, pos: Point },
+    /// A ship fired a bullet
+    Shot { ship: usize, pos: Point },
+    /// A bullet hit a body, which is now tethered to the ship that fired it
+    TetherCreated { ship: usize, body: usize, pos: Point }
+}
@@ -0,0 +1,82 @@
+//! The most recent states of a game, to look back at what just happened
+
+use std::collections::VecDeque;
+
+use game::Game;
+
+/// A ring buffer of game states, which drops the oldest state once it is full
+pub struct History {
+    states: VecDeque<Game>,
+    capacity: usize
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            states: VecDeque::with_capacity(capacity),
+            capacity: capacity
+        }
+    }
+
+    pub fn push(&mut self, game: &Game) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(game.clone());
+    }
+
+    /// The state pushed last
+    pub fn latest(&self) -> Option<&Game> {
+        self.states.back()
+    }
+
+    /// All states from the oldest to the newest
+    pub fn states(&self) -> Vec<Game> {
+        self.states.iter().cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+    use game::input::Actions;
+
+    /// The times of the states in `history`
+    fn times(history: &History) -> Vec<f64> {
+        history.states().iter().map(|game| game.sim.time).collect()
+    }
+
+    #[test]
+    fn the_oldest_states_are_dropped_when_full() {
+        let mut game = Game::with_seed(ARENA_SIZE, 0);
+        let mut history = History::new(3);
+        let mut pushed = vec![];
+        for _ in 0..5 {
+            history.push(&game);
+            pushed.push(game.sim.time);
+            game.control(vec![Actions::default(); 2]);
+            game.timestep();
+        }
+        assert_eq!(times(&history), pushed[2..].to_vec());
+        assert_eq!(history.latest().map(|game| game.sim.time), Some(pushed[4]));
+        history.clear();
+        assert!(history.latest().is_none());
+        assert!(history.states().is_empty());
+    }
+
+    #[test]
+    fn histories_without_capacity_stay_empty() {
+        let mut history = History::new(0);
+        history.push(&Game::with_seed(ARENA_SIZE, 0));
+        assert!(history.latest().is_none());
+        assert!(history.states().is_empty());
+    }
+}
@@ -1,12 +1,14 @@
+#[cfg(feature = "graphics")]
 use piston_window::{ControllerButton, ControllerAxisArgs, Key};
 
+#[cfg(feature = "graphics")]
 #[derive(Default)]
 pub struct InputController {
     actions: Vec<Actions>,
     pub draw_help: bool
 }
 
-#[derive(Default, Debug, Copy, Clone)]
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
 pub struct Actions {
     pub rotate_left: bool,
     pub rotate_right: bool,
@@ -15,6 +17,33 @@ pub struct Actions {
     pub respawn: bool
 }
 
+impl Actions {
+    /// Pack the actions into a single byte, e.g. for sending them over the network
+    pub fn to_bits(&self) -> u8 {
+        (self.rotate_left as u8)
+            | (self.rotate_right as u8) << 1
+            | (self.boost as u8) << 2
+            | (self.shoot as u8) << 3
+            | (self.respawn as u8) << 4
+    }
+
+    pub fn from_bits(bits: u8) -> Actions {
+        Actions {
+            rotate_left: bits & 1 != 0,
+            rotate_right: bits & 1 << 1 != 0,
+            boost: bits & 1 << 2 != 0,
+            shoot: bits & 1 << 3 != 0,
+            respawn: bits & 1 << 4 != 0
+        }
+    }
+
+    /// Actions active in either `self` or `other`
+    pub fn merge(&self, other: Actions) -> Actions {
+        Actions::from_bits(self.to_bits() | other.to_bits())
+    }
+}
+
+#[cfg(feature = "graphics")]
 impl InputController {
     pub fn new(num_players: usize) -> InputController {
         let mut actions = vec![];
@@ -1,15 +1,27 @@
-extern crate rand;
-
+pub mod bot;
+pub mod event;
+pub mod history;
 pub mod input;
 pub mod object;
+pub mod replay;
+pub mod rng;
+pub mod save;
 pub mod spring;
+pub mod stats;
+
+use std::time::Instant;
+
+use rand::{self, Rng};
 
 use self::input::{ Actions};
+use self::event::GameEvent;
 use self::object::{Object,ObjectType};
 use self::spring::Spring;
+use self::rng::GameRng;
 use ::simulation;
 use ::simulation::body::Body;
 use ::point::Point;
+use ::profiler::PhaseTimings;
 use ::simulation::Wall;
 
 const TURN_VEL : f64 = 5.0;
@@ -31,7 +43,7 @@ const MIN_MASS_BLACKHOLE: f64 = 70.0;
 const MAX_MASS_BLACKHOLE: f64 = 100.0;
 
 const SPRING_STRENGTH : f64 = 5.0;
-const SPRING_REST_LENGTH : f64 = 50.0;
+pub const SPRING_REST_LENGTH : f64 = 50.0;
 
 const NUM_SHIPS : usize = 2;
 const NUM_STARS : usize = 40;
@@ -43,11 +55,24 @@ const BLACKHOLE_SCORE: i32 = -100;
 const TOP_MARGIN: f64 = 200.0;
 const LEFT_MARGIN: f64 = 200.0;
 
+const SPAWN_ATTEMPTS: usize = 50;
+const SPAWN_MARGIN: f64 = 100.0;
+const SPAWN_CLEARANCE: f64 = 50.0;
+const SPAWN_BLACKHOLE_CLEARANCE: f64 = 300.0;
+const SPAWN_OPPONENT_CLEARANCE: f64 = 300.0;
+pub const SPAWN_PROTECTION_TIME: f64 = 2.0;
+
+/// Size of the arena, independent of the screen so every player gets the same map
+pub const ARENA_SIZE: Point = Point{x: 1920.0, y: 1080.0};
+/// Ticks per second of the game loop, each a `timestep` of the game
+pub const TICK_RATE: u64 = 60;
+
 pub const DISTANCE_SCALING: i32 = 2;
 pub const WALL_RESTITUTION: f64 = 0.5;
 pub const G : f64 = 1000.0;
 pub const FRICTION : f64 = 0.2;
 
+#[derive(Clone)]
 pub struct Game {
     pub objects: Vec<Object>,
     pub springs: Vec<Spring>,
@@ -55,14 +80,24 @@ pub struct Game {
     pub game_over: bool,
     pub score: Vec<i32>,
     pub should_respawn: Vec<bool>,
-    pub arena_size: Point
+    pub spawn_protection: Vec<f64>,
+    pub arena_size: Point,
+    pub rng: GameRng,
+    /// Events of the current tick, cleared when the next tick starts in `control`
+    pub events: Vec<GameEvent>
 }
 
 impl Game {
     pub fn new(arena_size: Point) -> Game{
-        let mut bodies = get_ships(NUM_SHIPS, arena_size);
-        bodies.append(&mut get_stars(NUM_STARS, arena_size));
-        bodies.append(&mut get_black_holes(NUM_BLACKHOLES, arena_size));
+        Game::with_seed(arena_size, rand::random())
+    }
+
+    /// Create a game whose layout and course only depend on `seed` and the actions of the players
+    pub fn with_seed(arena_size: Point, seed: u64) -> Game{
+        let mut rng = GameRng::new(seed);
+        let mut bodies = get_ships(NUM_SHIPS, arena_size, &mut rng);
+        bodies.append(&mut get_stars(NUM_STARS, arena_size, &mut rng));
+        bodies.append(&mut get_black_holes(NUM_BLACKHOLES, arena_size, &mut rng));
         bodies.push(get_mothership());
         let mut objects = vec![];
         for (i, b) in bodies.iter().enumerate() {
@@ -93,18 +128,40 @@ impl Game {
             game_over: false,
             score: vec![0, 0],
             should_respawn: vec![false, false],
-            arena_size: arena_size
+            spawn_protection: vec![0.0, 0.0],
+            arena_size: arena_size,
+            rng: rng,
+            events: vec![]
         }
     }
 
-    pub fn timestep(&mut self) {
+    /// Advance by one tick and return how long each of its phases took
+    pub fn timestep(&mut self) -> PhaseTimings {
+        let mut timings = PhaseTimings::default();
+        let start = Instant::now();
         self.handle_springs();
+        timings.record("springs", start);
+        let start = Instant::now();
         self.handle_bullets();
+        timings.record("bullets", start);
+        let start = Instant::now();
         self.handle_stars();
+        timings.record("stars", start);
+        let start = Instant::now();
         self.handle_blackholes();
+        timings.record("blackholes", start);
+        let start = Instant::now();
         self.handle_respawning();
+        timings.record("respawning", start);
+        let start = Instant::now();
         self.remove_objects();
-        self.sim.timestep();
+        timings.record("remove_objects", start);
+        timings.phases.extend(self.sim.timestep().phases);
+        self.collision_events();
+        if !self.objects.iter().any(|o| o.type_ == ObjectType::Star) {
+            self.game_over = true;
+        }
+        timings
     }
 
     pub fn remove_objects(&mut self) {
@@ -122,6 +179,18 @@ impl Game {
         self.springs.retain(|s| !s.should_be_removed);
     }
 
+    fn collision_events(&mut self) {
+        let sim = &self.sim;
+        for body in sim.bodies.iter() {
+            // Every collision is listed by both bodies. Bodies removed at the end of the step are skipped.
+            let others = body.did_collide.iter().filter(|&&other| other > body.id).filter_map(|&other| sim.find_body(other));
+            for other in others {
+                let pos = body.pos + (other.pos - body.pos).normalized() * body.radius;
+                self.events.push(GameEvent::Collision{pos: pos, speed: (body.vel - other.vel).norm()});
+            }
+        }
+    }
+
     pub fn handle_springs(&mut self) {
         for spring in self.springs.iter_mut() {
             let body1 = self.sim.get_body(spring.body1);
@@ -148,16 +217,21 @@ impl Game {
                     for &body in self.sim.get_body(star.body).did_collide.iter() {
                         if body == mothership_id {
                             star.should_be_removed = true;
+                            let mut delivered_by = None;
                             for spring in self.springs.iter() {
                                 if spring.body1 == star.body {
                                     if ship_bodies[0] == spring.body2 { 
-                                        self.score[0] += STAR_SCORE
+                                        self.score[0] += STAR_SCORE;
+                                        delivered_by = delivered_by.or(Some(0));
                                     }
                                     if ship_bodies[1] == spring.body2 { 
-                                        self.score[1] += STAR_SCORE
+                                        self.score[1] += STAR_SCORE;
+                                        delivered_by = delivered_by.or(Some(1));
                                     }
                                 }
                             }
+                            let pos = self.sim.get_body(star.body).pos;
+                            self.events.push(GameEvent::StarDelivered{ship: delivered_by, pos: pos});
                         }
                     }
                 },
@@ -174,9 +248,11 @@ impl Game {
                     for body in self.sim.get_body(blackhole.body).did_collide.iter() {
                         let ship = ship_bodies.iter().position(|b| b == body);
                         match ship {
-                            Some(ship_num) => {
+                            Some(ship_num) if self.sim.time >= self.spawn_protection[ship_num] => {
                                 self.score[ship_num] += BLACKHOLE_SCORE;
                                 self.should_respawn[ship_num] = true;
+                                let pos = self.sim.get_body(*body).pos;
+                                self.events.push(GameEvent::ShipSwallowed{ship: ship_num, pos: pos});
                             }
                             _ => {}
                         }
@@ -195,7 +271,8 @@ impl Game {
                 ObjectType::Bullet(ship_num, time) => {
                     for &body in self.sim.get_body(bullet.body).did_collide.iter() {
                         bullet.should_be_removed = true;
-                        if bullet_bodies.contains(&body) {
+                        // Bodies removed since the collision can't be tethered
+                        if bullet_bodies.contains(&body) || self.sim.find_body(body).is_none() {
                             continue;
                         }
                         let ship_body = ship_bodies[ship_num];
@@ -208,6 +285,8 @@ impl Game {
                             }
                             if add_spring {
                                 self.springs.push(Spring::new(body, ship_body));
+                                let pos = self.sim.get_body(body).pos;
+                                self.events.push(GameEvent::TetherCreated{ship: ship_num, body: body, pos: pos});
                             }
                         }
                     }
@@ -235,10 +314,15 @@ impl Game {
     }
 
     pub fn control(&mut self, actions: Vec<Actions>) {
+        self.events.clear();
         for ship_num in 0..NUM_SHIPS {
             let ship = self.get_ship(ship_num);
             Game::control_turning(&mut self.sim.get_body_mut(ship.body), actions[ship_num]);
             Game::control_moving(&mut self.sim.get_body_mut(ship.body), actions[ship_num]);
+            if actions[ship_num].boost {
+                let body = self.sim.get_body(ship.body);
+                self.events.push(GameEvent::Thrust{ship: ship_num, pos: body.pos, direction: Point::from_angle(body.apos)});
+            }
             self.control_shooting(ship_num, actions[ship_num]);
             self.control_respawning(ship_num, actions[ship_num]);
         }
@@ -249,6 +333,11 @@ impl Game {
         self.objects[index]
     }
 
+    /// The player whose ship a spring is attached to
+    pub fn spring_owner(&self, spring: &Spring) -> Option<usize> {
+        (0..self.score.len()).find(|&ship_num| self.get_ship(ship_num).body == spring.body2)
+    }
+
     pub fn get_ship_index(&self, ship_num: usize) -> usize {
         let index = self.objects.iter().enumerate().filter(|&(i, o)| o.type_ == ObjectType::Ship(ship_num)).next().unwrap().0;
         index
@@ -293,22 +382,27 @@ impl Game {
             self.sim.get_body_mut(ship.body).apply_impulse(-bullet.vel * BULLET_MASS);
             let index = self.sim.add_body(bullet);
             self.objects.push(Object::new(index, ObjectType::Bullet(ship_num, self.sim.time)));
+            self.events.push(GameEvent::Shot{ship: ship_num, pos: spawn_pos});
         }
     }
 
     pub fn control_respawning(&mut self, ship_num: usize, actions: Actions) {
         if actions.respawn {
             self.should_respawn[ship_num] = true;
+            let pos = self.sim.get_body(self.get_ship(ship_num).body).pos;
+            self.events.push(GameEvent::ShipReset{ship: ship_num, pos: pos});
         }
     }
 
     pub fn respawn_ship(&mut self, ship_number: usize) {
-        let mut new_ship = get_ship_body(self.arena_size);
+        let spawn_pos = self.find_spawn_position(ship_number);
+        let new_ship = get_ship_body(spawn_pos);
         let index = self.get_ship_index(ship_number);
         self.sim.get_body_mut(self.objects[index].body).should_be_removed = true;
         self.objects[index].should_be_removed = true;
         let index = self.sim.add_body(new_ship);
         self.objects.push(Object::new(index, ObjectType::Ship(ship_number)));
+        self.spawn_protection[ship_number] = self.sim.time + SPAWN_PROTECTION_TIME;
         for bullet in self.objects.iter_mut() {
             match bullet.type_ {
                 ObjectType::Bullet(ship_number_, _) => {
@@ -321,29 +415,70 @@ impl Game {
         }
     }
 
+    pub fn is_spawn_protected(&self, ship_num: usize) -> bool {
+        self.sim.time < self.spawn_protection[ship_num]
+    }
+
+    /// Sample random positions in the arena and return the one furthest away from
+    /// anything that could immediately harm a freshly spawned ship.
+    pub fn find_spawn_position(&mut self, ship_num: usize) -> Point {
+        let mut best_pos = Point{x: self.arena_size.x * 0.5, y: self.arena_size.y * 0.5};
+        let mut best_clearance = f64::NEG_INFINITY;
+        for _ in 0..SPAWN_ATTEMPTS {
+            let x = self.rng.gen::<f64>() * (self.arena_size.x - 2.0 * SPAWN_MARGIN) + SPAWN_MARGIN;
+            let y = self.rng.gen::<f64>() * (self.arena_size.y - 2.0 * SPAWN_MARGIN) + SPAWN_MARGIN;
+            let pos = Point{x: x, y: y};
+            let clearance = self.spawn_clearance(pos, ship_num);
+            if clearance > best_clearance {
+                best_clearance = clearance;
+                best_pos = pos;
+            }
+        }
+        best_pos
+    }
+
+    /// Free space around `pos` for a ship, after subtracting the safety distance required
+    /// for each kind of object. Negative values mean the position is unsafe.
+    fn spawn_clearance(&self, pos: Point, ship_num: usize) -> f64 {
+        let mut clearance = f64::INFINITY;
+        for object in self.objects.iter() {
+            let required = match object.type_ {
+                ObjectType::Ship(num) if num == ship_num => continue,
+                ObjectType::Bullet(_, _) => continue,
+                ObjectType::Ship(_) => SPAWN_OPPONENT_CLEARANCE,
+                ObjectType::BlackHole => SPAWN_BLACKHOLE_CLEARANCE,
+                ObjectType::Star | ObjectType::Mothership => SPAWN_CLEARANCE
+            };
+            let body = self.sim.get_body(object.body);
+            let distance = (body.pos - pos).norm() - body.radius - SHIP_RADIUS;
+            clearance = clearance.min(distance - required);
+        }
+        clearance
+    }
+
 }
 
-pub fn get_ship_body(arena_size: Point) -> Body {
-    let x = rand::random::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
-    let y = 50.0;
-    let mut b = Body::new(Point{x: x, y: y}, SHIP_MASS, SHIP_RADIUS);
+pub fn get_ship_body(pos: Point) -> Body {
+    let mut b = Body::new(pos, SHIP_MASS, SHIP_RADIUS);
     b.gravity_flag = 2;
     b
 }
 
-pub fn get_ships(num_ships: usize, arena_size: Point) -> Vec<Body> {
+pub fn get_ships<R: Rng>(num_ships: usize, arena_size: Point, rng: &mut R) -> Vec<Body> {
     let mut bodies : Vec<Body> = vec![];
     for i in 0..num_ships {
-        bodies.push(get_ship_body(arena_size))
+        let x = rng.gen::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
+        let y = 50.0;
+        bodies.push(get_ship_body(Point{x: x, y: y}))
     }
     bodies
 }
 
-pub fn get_stars(num_bodies: usize, arena_size: Point) -> Vec<Body> {
+pub fn get_stars<R: Rng>(num_bodies: usize, arena_size: Point, rng: &mut R) -> Vec<Body> {
     let mut bodies : Vec<Body> = vec![];
     for _ in 0..num_bodies {
-        let x = rand::random::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
-        let y = rand::random::<f64>() * (arena_size.y-TOP_MARGIN) + TOP_MARGIN;
+        let x = rng.gen::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
+        let y = rng.gen::<f64>() * (arena_size.y-TOP_MARGIN) + TOP_MARGIN;
         let mass = STAR_MASS;
         let radius = STAR_RADIUS;
         bodies.push(Body::new(Point{x: x, y: y}, mass, radius))
@@ -360,12 +495,12 @@ pub fn get_mothership() -> Body {
     Body::new(Point{x: x, y: y}, mass, radius)
 }
 
-pub fn get_black_holes(num_bodies: usize, arena_size: Point) -> Vec<Body> {
+pub fn get_black_holes<R: Rng>(num_bodies: usize, arena_size: Point, rng: &mut R) -> Vec<Body> {
     let mut bodies : Vec<Body> = vec![];
     for _ in 0..num_bodies {
-        let x = rand::random::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
-        let y = rand::random::<f64>() * (arena_size.y-TOP_MARGIN) + TOP_MARGIN;
-        let mass = rand::random::<f64>() * (MAX_MASS_BLACKHOLE-MIN_MASS_BLACKHOLE) + MIN_MASS_BLACKHOLE;
+        let x = rng.gen::<f64>() * (arena_size.x-LEFT_MARGIN) + LEFT_MARGIN;
+        let y = rng.gen::<f64>() * (arena_size.y-TOP_MARGIN) + TOP_MARGIN;
+        let mass = rng.gen::<f64>() * (MAX_MASS_BLACKHOLE-MIN_MASS_BLACKHOLE) + MIN_MASS_BLACKHOLE;
         let radius = 10.0 * mass.sqrt();
         let mut b = Body::new(Point{x: x, y: y}, mass, radius);
         b.gravity_flag = 1;
@@ -373,3 +508,70 @@ pub fn get_black_holes(num_bodies: usize, arena_size: Point) -> Vec<Body> {
     }
     bodies
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(game: &mut Game) {
+        game.control(vec![Actions::default(); NUM_SHIPS]);
+        game.timestep();
+    }
+
+    /// Move the ship of `ship_num` onto the edge of the first black hole, returning the black hole's body
+    fn touch_black_hole(game: &mut Game, ship_num: usize) -> usize {
+        let black_hole = game.objects.iter().find(|o| o.type_ == ObjectType::BlackHole).unwrap().body;
+        let (pos, radius) = {
+            let body = game.sim.get_body(black_hole);
+            (body.pos, body.radius)
+        };
+        let ship = game.sim.get_body_mut(game.get_ship(ship_num).body);
+        ship.pos = pos + Point{x: radius + SHIP_RADIUS * 0.5, y: 0.0};
+        ship.vel = Point{x: 0.0, y: 0.0};
+        black_hole
+    }
+
+    fn swallowed(game: &Game) -> bool {
+        game.events.iter().any(|event| matches!(*event, GameEvent::ShipSwallowed{ship: 0, ..}))
+    }
+
+    #[test]
+    fn respawned_ships_keep_away_from_black_holes() {
+        for seed in 0..20 {
+            let mut game = Game::with_seed(ARENA_SIZE, seed);
+            game.respawn_ship(0);
+            step(&mut game);
+            let ship = game.sim.get_body(game.get_ship(0).body).clone();
+            for object in game.objects.iter().filter(|o| o.type_ == ObjectType::BlackHole) {
+                let black_hole = game.sim.get_body(object.body);
+                let gap = (black_hole.pos - ship.pos).norm() - black_hole.radius - ship.radius;
+                assert!(gap >= SPAWN_BLACKHOLE_CLEARANCE, "Seed {}: respawned {} from a black hole", seed, gap);
+            }
+        }
+    }
+
+    #[test]
+    fn protected_ships_survive_black_holes() {
+        let mut game = Game::with_seed(ARENA_SIZE, 4);
+        game.respawn_ship(0);
+        step(&mut game);
+        assert!(game.is_spawn_protected(0));
+        let score = game.score[0];
+        let mut touched = false;
+        for _ in 0..10 {
+            let black_hole = touch_black_hole(&mut game, 0);
+            step(&mut game);
+            touched |= game.sim.get_body(black_hole).did_collide.contains(&game.get_ship(0).body);
+            assert!(!swallowed(&game));
+        }
+        assert!(touched);
+        assert!(game.is_spawn_protected(0));
+        assert_eq!(game.score[0], score);
+        // Without protection the same contact is fatal
+        game.spawn_protection[0] = game.sim.time;
+        touch_black_hole(&mut game, 0);
+        // Contacts are handled in the tick after the one they happen in
+        assert!((0..2).any(|_| { step(&mut game); swallowed(&game) }));
+        assert_eq!(game.score[0], score + BLACKHOLE_SCORE);
+    }
+}
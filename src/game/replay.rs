@@ -0,0 +1,182 @@
+//! Recordings of matches: a snapshot of the game they start from and the actions of all players
+//! in every tick. Since the game is deterministic, this is enough to simulate the match again.
+//!
+//! ```text
+//! amazing_grame_replay 1
+//! snapshot 57
+//! (57 lines of the snapshot, see `save`)
+//! 0 4
+//! 4 12
+//! ```
+//!
+//! Every line after the snapshot holds the actions of the players in one tick, see `Actions::to_bits`.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use game::Game;
+use game::input::Actions;
+
+const MAGIC: &'static str = "amazing_grame_replay";
+pub const REPLAY_VERSION: u32 = 1;
+
+pub struct Replay {
+    /// The saved game the replay starts from
+    pub snapshot: String,
+    pub ticks: Vec<Vec<Actions>>
+}
+
+impl Replay {
+    /// Start recording a match from the current state of `game`
+    pub fn new(game: &Game) -> Replay {
+        Replay {
+            snapshot: game.save(),
+            ticks: vec![]
+        }
+    }
+
+    /// Add the actions with which the next tick is simulated
+    pub fn record(&mut self, actions: &[Actions]) {
+        self.ticks.push(actions.to_vec());
+    }
+
+    /// The game at the start of the replay
+    pub fn start(&self) -> Result<Game, String> {
+        Game::load(&self.snapshot).map_err(|e| format!("Invalid snapshot: {}", e))
+    }
+
+    pub fn save(&self) -> String {
+        let mut text = format!("{} {}\nsnapshot {}\n{}", MAGIC, REPLAY_VERSION, self.snapshot.lines().count(), self.snapshot);
+        for actions in self.ticks.iter() {
+            let bits: Vec<String> = actions.iter().map(|a| a.to_bits().to_string()).collect();
+            text.push_str(&bits.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn load(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+        if header.len() != 2 || header[0] != MAGIC {
+            return Err("Not a replay file".to_string());
+        }
+        if header[1] != REPLAY_VERSION.to_string() {
+            return Err(format!("Unsupported replay version {} (expected {})", header[1], REPLAY_VERSION));
+        }
+        let snapshot_lines = match lines.next().map(|line| line.split_whitespace().collect::<Vec<_>>()) {
+            Some(ref fields) if fields.len() == 2 && fields[0] == "snapshot" => {
+                fields[1].parse::<usize>().map_err(|_| format!("Invalid snapshot length {}", fields[1]))?
+            }
+            _ => return Err("Missing snapshot".to_string())
+        };
+        let mut snapshot = String::new();
+        for _ in 0..snapshot_lines {
+            snapshot.push_str(lines.next().ok_or("Snapshot ends early".to_string())?);
+            snapshot.push('\n');
+        }
+        let mut ticks = vec![];
+        for (number, line) in lines.enumerate() {
+            let actions = line.split_whitespace()
+                .map(|field| field.parse::<u8>().map(Actions::from_bits))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("Tick {}: invalid actions", number))?;
+            ticks.push(actions);
+        }
+        let replay = Replay { snapshot: snapshot, ticks: ticks };
+        let players = replay.start()?.score.len();
+        if let Some(number) = replay.ticks.iter().position(|actions| actions.len() != players) {
+            return Err(format!("Tick {}: expected actions of {} players", number, players));
+        }
+        Ok(replay)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        File::create(path)?.write_all(self.save().as_bytes())
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Replay, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Replay::load(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+
+    const TICKS: usize = 400;
+
+    /// Actions which make both ships fly, turn and shoot in a fixed pattern
+    fn scripted_actions(tick: usize) -> Vec<Actions> {
+        vec![
+            Actions { boost: tick % 4 != 0, rotate_left: tick % 60 < 15, shoot: tick % 45 == 0, ..Actions::default() },
+            Actions { boost: tick % 2 == 0, rotate_right: tick % 80 < 30, shoot: tick % 35 == 0, respawn: tick == 300, ..Actions::default() }
+        ]
+    }
+
+    /// A recording of a scripted match, and the game at its end
+    fn recorded_match() -> (Replay, Game) {
+        let mut game = Game::with_seed(ARENA_SIZE, 11);
+        let mut replay = Replay::new(&game);
+        for tick in 0..TICKS {
+            let actions = scripted_actions(tick);
+            replay.record(&actions);
+            game.control(actions);
+            game.timestep();
+        }
+        (replay, game)
+    }
+
+    #[test]
+    fn replays_simulate_the_same_match() {
+        let (replay, live) = recorded_match();
+        let loaded = Replay::load(&replay.save()).unwrap();
+        assert_eq!(loaded.ticks.len(), TICKS);
+        let mut game = loaded.start().unwrap();
+        for actions in loaded.ticks.iter() {
+            game.control(actions.clone());
+            game.timestep();
+        }
+        assert_eq!(game.sim.state_hash(), live.sim.state_hash());
+        assert_eq!(game.save(), live.save());
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let text = recorded_match().0.save();
+        assert!(Replay::load("").is_err());
+        assert!(Replay::load(&text.replacen(MAGIC, "amazing_grame_save", 1)).is_err());
+        let newer = format!("{} {}", MAGIC, REPLAY_VERSION + 1);
+        assert!(Replay::load(&text.replacen(&format!("{} {}", MAGIC, REPLAY_VERSION), &newer, 1)).is_err());
+    }
+
+    #[test]
+    fn short_snapshots_are_rejected() {
+        let replay = recorded_match().0;
+        let lines = replay.snapshot.lines().count();
+        let text = replay.save();
+        let too_long = text.replacen(&format!("snapshot {}", lines), &format!("snapshot {}", lines + TICKS + 10), 1);
+        assert!(Replay::load(&too_long).is_err());
+        // The rest of the snapshot would be read as ticks
+        let too_short = text.replacen(&format!("snapshot {}", lines), &format!("snapshot {}", lines - 3), 1);
+        assert!(Replay::load(&too_short).is_err());
+        let mut cut_snapshot = replay.save().lines().take(lines - 5).collect::<Vec<_>>().join("\n");
+        cut_snapshot.push('\n');
+        assert!(Replay::load(&cut_snapshot).is_err());
+    }
+
+    #[test]
+    fn ticks_with_the_wrong_number_of_players_are_rejected() {
+        let mut replay = recorded_match().0;
+        replay.ticks[7].push(Actions::default());
+        assert!(Replay::load(&replay.save()).is_err());
+        replay.ticks[7].truncate(1);
+        assert!(Replay::load(&replay.save()).is_err());
+    }
+}
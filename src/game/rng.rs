@@ -0,0 +1,33 @@
+use rand::Rng;
+
+/// Small xorshift random number generator driving all randomness of a `Game`.
+/// Its whole state is a single number, so games can be reproduced from a seed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameRng {
+    pub state: u64
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        // Scramble the seed (splitmix64) so that similar seeds give unrelated sequences
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        // Xorshift gets stuck at zero
+        GameRng { state: if z == 0 { 1 } else { z } }
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
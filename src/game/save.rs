@@ -0,0 +1,309 @@
+//! Plain text snapshots of the complete game state. Every line holds one record, starting with its kind:
+//!
+//! ```text
+//! amazing_grame_save 2
+//! arena_size 1920 1080
+//! body 0 150 150 0 0 0 0 0 0 0 0 0 1000 50 1250000 0 0
+//! object 0 mothership
+//! spring 12 0 -1.5 3.25
+//! ```
+//!
+//! Numbers are written with full precision, so loading a snapshot resumes the exact same game.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+
+use ::point::Point;
+use game::{Game, NUM_SHIPS};
+use game::object::{Object, ObjectType};
+use game::rng::GameRng;
+use game::spring::Spring;
+use simulation::{Simulation, Wall};
+use simulation::body::Body;
+
+const MAGIC: &'static str = "amazing_grame_save";
+pub const SAVE_VERSION: u32 = 2;
+
+impl Game {
+    pub fn save(&self) -> String {
+        let mut lines = vec![];
+        lines.push(format!("{} {}", MAGIC, SAVE_VERSION));
+        lines.push(format!("arena_size {} {}", self.arena_size.x, self.arena_size.y));
+        lines.push(format!("rng {}", self.rng.state));
+        lines.push(format!("time {}", self.sim.time));
+        lines.push(format!("next_id {}", self.sim.next_id));
+        lines.push(format!("game_over {}", self.game_over as u8));
+        lines.push(format!("score {}", join(&self.score)));
+        lines.push(format!("should_respawn {}", join(&self.should_respawn.iter().map(|&b| b as u8).collect::<Vec<_>>())));
+        lines.push(format!("spawn_protection {}", join(&self.spawn_protection)));
+        for wall in self.sim.walls.iter() {
+            lines.push(format!("wall {} {} {} {}", wall.pos.x, wall.pos.y, wall.normal.x, wall.normal.y));
+        }
+        for b in self.sim.bodies.iter() {
+            lines.push(format!("body {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                               b.id, b.pos.x, b.pos.y, b.vel.x, b.vel.y, b.acc.x, b.acc.y, b.last_acc.x, b.last_acc.y,
+                               b.apos, b.avel, b.aacc, b.mass, b.radius, b.moment_of_inertia,
+                               b.gravity_flag, b.should_be_removed as u8, join(&b.did_collide)).trim_end().to_string());
+        }
+        for object in self.objects.iter() {
+            let type_ = match object.type_ {
+                ObjectType::Star => "star".to_string(),
+                ObjectType::BlackHole => "blackhole".to_string(),
+                ObjectType::Mothership => "mothership".to_string(),
+                ObjectType::Ship(num) => format!("ship {}", num),
+                ObjectType::Bullet(num, time) => format!("bullet {} {}", num, time)
+            };
+            lines.push(format!("object {} {}", object.body, type_));
+        }
+        for spring in self.springs.iter() {
+            lines.push(format!("spring {} {} {} {}", spring.body1, spring.body2, spring.force.x, spring.force.y));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn load(text: &str) -> Result<Game, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) => {
+                let mut fields = header.split_whitespace();
+                if fields.next() != Some(MAGIC) {
+                    return Err("Not a save file".to_string());
+                }
+                let version: u32 = parse(&mut fields).map_err(|e| format!("Invalid header: {}", e))?;
+                if version != SAVE_VERSION {
+                    return Err(format!("Unsupported save version {} (expected {})", version, SAVE_VERSION));
+                }
+            }
+            None => return Err("Empty save file".to_string())
+        }
+        let mut game = Game {
+            objects: vec![],
+            springs: vec![],
+            sim: Simulation::new(vec![], vec![]),
+            game_over: false,
+            score: vec![],
+            should_respawn: vec![],
+            spawn_protection: vec![],
+            arena_size: Point{x: 0.0, y: 0.0},
+            rng: GameRng::new(0),
+            events: vec![]
+        };
+        for (number, line) in lines {
+            game.load_line(line).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+        }
+        if game.score.len() != game.should_respawn.len() || game.score.len() != game.spawn_protection.len() {
+            return Err("Inconsistent number of players".to_string());
+        }
+        if game.score.len() != NUM_SHIPS {
+            return Err(format!("Expected {} players, found {}", NUM_SHIPS, game.score.len()));
+        }
+        for object in game.objects.iter() {
+            match object.type_ {
+                ObjectType::Ship(num) | ObjectType::Bullet(num, _) if num >= NUM_SHIPS => {
+                    return Err(format!("Object of player {} in a game of {} players", num, NUM_SHIPS));
+                }
+                _ => {}
+            }
+        }
+        for ship_num in 0..game.score.len() {
+            if !game.objects.iter().any(|o| o.type_ == ObjectType::Ship(ship_num)) {
+                return Err(format!("Missing ship of player {}", ship_num));
+            }
+        }
+        if !game.objects.iter().any(|o| o.type_ == ObjectType::Mothership) {
+            return Err("Missing mothership".to_string());
+        }
+        if game.sim.bodies.iter().any(|b| b.id >= game.sim.next_id) {
+            return Err(format!("Body id beyond next_id {}", game.sim.next_id));
+        }
+        for object in game.objects.iter() {
+            if !game.sim.bodies.iter().any(|b| b.id == object.body) {
+                return Err(format!("Object refers to missing body {}", object.body));
+            }
+        }
+        for spring in game.springs.iter() {
+            if !game.sim.bodies.iter().any(|b| b.id == spring.body1) || !game.sim.bodies.iter().any(|b| b.id == spring.body2) {
+                return Err(format!("Spring refers to missing body {} or {}", spring.body1, spring.body2));
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        File::create(path)?.write_all(self.save().as_bytes())
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Game, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Game::load(&text)
+    }
+
+    fn load_line(&mut self, line: &str) -> Result<(), String> {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("arena_size") => self.arena_size = Point{x: parse(&mut fields)?, y: parse(&mut fields)?},
+            Some("rng") => self.rng = GameRng{state: parse(&mut fields)?},
+            Some("time") => self.sim.time = parse(&mut fields)?,
+            Some("next_id") => self.sim.next_id = parse(&mut fields)?,
+            Some("game_over") => self.game_over = parse::<u8>(&mut fields)? != 0,
+            Some("score") => self.score = parse_all(&mut fields)?,
+            Some("should_respawn") => self.should_respawn = parse_all::<u8>(&mut fields)?.iter().map(|&b| b != 0).collect(),
+            Some("spawn_protection") => self.spawn_protection = parse_all(&mut fields)?,
+            Some("wall") => self.sim.walls.push(Wall{
+                pos: Point{x: parse(&mut fields)?, y: parse(&mut fields)?},
+                normal: Point{x: parse(&mut fields)?, y: parse(&mut fields)?}
+            }),
+            Some("body") => {
+                let id = parse(&mut fields)?;
+                let pos = Point{x: parse(&mut fields)?, y: parse(&mut fields)?};
+                let mut body = Body::new(pos, 1.0, 1.0);
+                body.id = id;
+                body.vel = Point{x: parse(&mut fields)?, y: parse(&mut fields)?};
+                body.acc = Point{x: parse(&mut fields)?, y: parse(&mut fields)?};
+                body.last_acc = Point{x: parse(&mut fields)?, y: parse(&mut fields)?};
+                body.apos = parse(&mut fields)?;
+                body.avel = parse(&mut fields)?;
+                body.aacc = parse(&mut fields)?;
+                body.mass = parse(&mut fields)?;
+                body.radius = parse(&mut fields)?;
+                body.moment_of_inertia = parse(&mut fields)?;
+                body.gravity_flag = parse(&mut fields)?;
+                body.should_be_removed = parse::<u8>(&mut fields)? != 0;
+                body.did_collide = parse_all(&mut fields)?;
+                self.sim.bodies.push(body);
+            }
+            Some("object") => {
+                let body = parse(&mut fields)?;
+                let type_ = match fields.next() {
+                    Some("star") => ObjectType::Star,
+                    Some("blackhole") => ObjectType::BlackHole,
+                    Some("mothership") => ObjectType::Mothership,
+                    Some("ship") => ObjectType::Ship(parse(&mut fields)?),
+                    Some("bullet") => ObjectType::Bullet(parse(&mut fields)?, parse(&mut fields)?),
+                    other => return Err(format!("Unknown object type {:?}", other))
+                };
+                self.objects.push(Object::new(body, type_));
+            }
+            Some("spring") => {
+                let mut spring = Spring::new(parse(&mut fields)?, parse(&mut fields)?);
+                spring.force = Point{x: parse(&mut fields)?, y: parse(&mut fields)?};
+                self.springs.push(spring);
+            }
+            Some(other) => return Err(format!("Unknown record {}", other)),
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+fn parse<T: FromStr>(fields: &mut SplitWhitespace) -> Result<T, String> {
+    let field = fields.next().ok_or("Missing value".to_string())?;
+    field.parse().map_err(|_| format!("Invalid value {}", field))
+}
+
+fn parse_all<T: FromStr>(fields: &mut SplitWhitespace) -> Result<Vec<T>, String> {
+    let mut values = vec![];
+    while let Some(field) = fields.next() {
+        values.push(field.parse().map_err(|_| format!("Invalid value {}", field))?);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+    use game::input::Actions;
+
+    /// A game in which both ships flew, turned and shot for a while
+    fn played_game() -> Game {
+        let mut game = Game::with_seed(ARENA_SIZE, 3);
+        for tick in 0..300 {
+            let first = Actions { boost: true, rotate_left: tick % 50 < 20, shoot: tick % 40 == 0, ..Actions::default() };
+            let second = Actions { boost: tick % 3 == 0, rotate_right: true, shoot: tick % 25 == 0, ..Actions::default() };
+            game.control(vec![first, second]);
+            game.timestep();
+        }
+        game
+    }
+
+    /// The save of `game` with every line passed through `change`
+    fn edited_save<F: Fn(&str) -> String>(game: &Game, change: F) -> String {
+        game.save().lines().map(|line| change(line) + "\n").collect()
+    }
+
+    #[test]
+    fn loading_a_save_resumes_the_same_game() {
+        let mut game = played_game();
+        let text = game.save();
+        let mut loaded = Game::load(&text).unwrap();
+        assert_eq!(loaded.save(), text);
+        // The physics overlay draws the acceleration of the last step
+        for (body, loaded_body) in game.sim.bodies.iter().zip(loaded.sim.bodies.iter()) {
+            assert_eq!(loaded_body.last_acc, body.last_acc);
+        }
+        for _ in 0..100 {
+            game.control(vec![Actions { boost: true, ..Actions::default() }; 2]);
+            game.timestep();
+            loaded.control(vec![Actions { boost: true, ..Actions::default() }; 2]);
+            loaded.timestep();
+        }
+        assert_eq!(loaded.save(), game.save());
+    }
+
+    #[test]
+    fn truncated_saves_are_rejected() {
+        let text = played_game().save();
+        assert!(Game::load("").is_err());
+        assert!(Game::load("amazing_grame_save").is_err());
+        // Everything up to the objects is needed to find the ships
+        let objects = text.find("\nobject").unwrap();
+        for end in (0..objects).step_by(97) {
+            assert!(Game::load(&text[..end]).is_err(), "Loaded a save cut after {} bytes", end);
+        }
+    }
+
+    #[test]
+    fn foreign_and_newer_saves_are_rejected() {
+        let game = played_game();
+        assert!(Game::load(&game.save().replacen(MAGIC, "something_else", 1)).is_err());
+        let newer = edited_save(&game, |line| if line.starts_with(MAGIC) { format!("{} {}", MAGIC, SAVE_VERSION + 1) } else { line.to_string() });
+        assert!(Game::load(&newer).is_err());
+    }
+
+    #[test]
+    fn inconsistent_saves_are_rejected() {
+        let game = played_game();
+        let one_score = edited_save(&game, |line| if line.starts_with("score") { "score 0".to_string() } else { line.to_string() });
+        assert!(Game::load(&one_score).is_err());
+        let one_player = edited_save(&game, |line| match line.split_whitespace().next() {
+            Some(name @ "score") | Some(name @ "should_respawn") | Some(name @ "spawn_protection") => format!("{} 0", name),
+            _ => line.to_string()
+        });
+        assert!(Game::load(&one_player).is_err());
+        let three_players = edited_save(&game, |line| match line.split_whitespace().next() {
+            Some("score") | Some("should_respawn") | Some("spawn_protection") => format!("{} 0", line),
+            _ => line.to_string()
+        });
+        assert!(Game::load(&three_players).is_err());
+        let foreign_ship = format!("{}object 3 ship 5\n", game.save());
+        assert!(Game::load(&foreign_ship).is_err());
+        let foreign_bullet = format!("{}object 3 bullet 2 0.5\n", game.save());
+        assert!(Game::load(&foreign_bullet).is_err());
+        let missing_body = format!("{}object {} star\n", game.save(), game.sim.next_id + 10);
+        assert!(Game::load(&missing_body).is_err());
+        let broken_spring = format!("{}spring 0 {} 0 0\n", game.save(), game.sim.next_id + 10);
+        assert!(Game::load(&broken_spring).is_err());
+        let unknown = format!("{}teleporter 1 2\n", game.save());
+        assert!(Game::load(&unknown).is_err());
+    }
+}
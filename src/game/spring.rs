@@ -1,6 +1,6 @@
 use ::point::Point;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Spring {
     pub body1: usize,
     pub body2: usize,
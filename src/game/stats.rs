@@ -0,0 +1,143 @@
+//! Statistics of a match, counted from the events and ships of every tick. They can be written
+//! as JSON or, with one row per player, as CSV.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use game::{Game, TICK_RATE};
+use game::event::GameEvent;
+use simulation;
+
+/// Seconds of the game loop per tick
+const TICK_DURATION: f64 = 1.0 / TICK_RATE as f64;
+
+#[derive(Clone, Debug, Default)]
+pub struct PlayerStats {
+    pub score: i32,
+    pub shots: u32,
+    pub tethers: u32,
+    pub stars_delivered: u32,
+    /// Times the ship was swallowed by a black hole
+    pub deaths: u32,
+    /// Times the player reset their ship
+    pub resets: u32,
+    /// Distance the ship flew, in arena units
+    pub distance: f64,
+    /// Seconds the engine was running
+    pub boost_time: f64
+}
+
+pub struct MatchStats {
+    pub players: Vec<PlayerStats>,
+    pub ticks: u64
+}
+
+impl MatchStats {
+    pub fn new(num_players: usize) -> MatchStats {
+        MatchStats {
+            players: vec![PlayerStats::default(); num_players],
+            ticks: 0
+        }
+    }
+
+    /// Seconds since the start of the match
+    pub fn duration(&self) -> f64 {
+        self.ticks as f64 * TICK_DURATION
+    }
+
+    /// Count the last tick of `game`
+    pub fn record(&mut self, game: &Game) {
+        self.ticks += 1;
+        for event in game.events.iter() {
+            match *event {
+                GameEvent::Shot { ship, .. } => self.players[ship].shots += 1,
+                GameEvent::TetherCreated { ship, .. } => self.players[ship].tethers += 1,
+                GameEvent::StarDelivered { ship: Some(ship), .. } => self.players[ship].stars_delivered += 1,
+                GameEvent::ShipSwallowed { ship, .. } => self.players[ship].deaths += 1,
+                GameEvent::ShipReset { ship, .. } => self.players[ship].resets += 1,
+                GameEvent::Thrust { ship, .. } => self.players[ship].boost_time += TICK_DURATION,
+                _ => {}
+            }
+        }
+        for (ship_num, player) in self.players.iter_mut().enumerate() {
+            player.score = game.score[ship_num];
+            player.distance += game.sim.get_body(game.get_ship(ship_num).body).vel.norm() * simulation::DT;
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let players: Vec<String> = self.players.iter().enumerate().map(|(i, player)| {
+            format!("    {{\"player\": {}, \"score\": {}, \"shots\": {}, \"tethers\": {}, \"stars_delivered\": {}, \"deaths\": {}, \"resets\": {}, \"distance\": {:.1}, \"boost_time\": {:.2}}}",
+                i + 1, player.score, player.shots, player.tethers, player.stars_delivered, player.deaths, player.resets, player.distance, player.boost_time)
+        }).collect();
+        format!("{{\n  \"ticks\": {},\n  \"duration\": {:.2},\n  \"players\": [\n{}\n  ]\n}}\n", self.ticks, self.duration(), players.join(",\n"))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut text = "player,score,shots,tethers,stars_delivered,deaths,resets,distance,boost_time\n".to_string();
+        for (i, player) in self.players.iter().enumerate() {
+            text.push_str(&format!("{},{},{},{},{},{},{},{:.1},{:.2}\n",
+                i + 1, player.score, player.shots, player.tethers, player.stars_delivered, player.deaths, player.resets, player.distance, player.boost_time));
+        }
+        text
+    }
+
+    /// Write the statistics as CSV if `path` ends with `.csv` and as JSON otherwise
+    pub fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let text = if path.extension().is_some_and(|extension| extension == "csv") {
+            self.to_csv()
+        }
+        else {
+            self.to_json()
+        };
+        File::create(path)?.write_all(text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+    use game::input::Actions;
+
+    fn example() -> MatchStats {
+        let mut stats = MatchStats::new(2);
+        stats.ticks = 90;
+        stats.players[0] = PlayerStats { score: 200, shots: 3, tethers: 2, stars_delivered: 2, deaths: 0, resets: 1, distance: 1234.56, boost_time: 1.25 };
+        stats.players[1] = PlayerStats { score: -100, deaths: 1, ..PlayerStats::default() };
+        stats
+    }
+
+    #[test]
+    fn json_lists_every_player() {
+        assert_eq!(example().to_json(), "{\n  \"ticks\": 90,\n  \"duration\": 1.50,\n  \"players\": [\n\
+            \x20   {\"player\": 1, \"score\": 200, \"shots\": 3, \"tethers\": 2, \"stars_delivered\": 2, \"deaths\": 0, \"resets\": 1, \"distance\": 1234.6, \"boost_time\": 1.25},\n\
+            \x20   {\"player\": 2, \"score\": -100, \"shots\": 0, \"tethers\": 0, \"stars_delivered\": 0, \"deaths\": 1, \"resets\": 0, \"distance\": 0.0, \"boost_time\": 0.00}\n\
+            \x20 ]\n}\n");
+    }
+
+    #[test]
+    fn csv_has_a_row_per_player() {
+        assert_eq!(example().to_csv(), "player,score,shots,tethers,stars_delivered,deaths,resets,distance,boost_time\n\
+            1,200,3,2,2,0,1,1234.6,1.25\n\
+            2,-100,0,0,0,1,0,0.0,0.00\n");
+    }
+
+    #[test]
+    fn times_are_in_seconds_of_the_game_loop() {
+        let mut game = Game::with_seed(ARENA_SIZE, 0);
+        let mut stats = MatchStats::new(2);
+        let boosting = Actions { boost: true, ..Actions::default() };
+        for _ in 0..TICK_RATE {
+            game.control(vec![boosting, Actions::default()]);
+            game.timestep();
+            stats.record(&game);
+        }
+        assert_eq!(stats.ticks, TICK_RATE);
+        assert!((stats.duration() - 1.0).abs() < 1e-9);
+        assert!((stats.players[0].boost_time - 1.0).abs() < 1e-9);
+        assert_eq!(stats.players[1].boost_time, 0.0);
+        assert!(stats.players[0].distance > 0.0);
+    }
+}
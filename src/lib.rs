@@ -0,0 +1,26 @@
+extern crate rand;
+#[cfg(feature = "graphics")]
+extern crate piston_window;
+#[cfg(feature = "graphics")]
+extern crate opengl_graphics;
+#[cfg(feature = "graphics")]
+extern crate image;
+#[cfg(feature = "graphics")]
+extern crate rusttype;
+#[cfg(feature = "graphics")]
+extern crate gif;
+#[cfg(feature = "audio")]
+extern crate rodio;
+
+pub mod assets;
+pub mod point;
+pub mod profiler;
+pub mod audio;
+pub mod simulation;
+pub mod game;
+pub mod env;
+pub mod net;
+#[cfg(feature = "graphics")]
+pub mod render;
+#[cfg(feature = "graphics")]
+pub mod resources;
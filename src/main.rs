@@ -1,68 +1,492 @@
 extern crate piston_window;
 extern crate opengl_graphics;
+extern crate amazing_grame;
+extern crate rand;
 
-use piston_window::{Button,EventLoop, Input, OpenGL, PistonWindow, WindowSettings,Motion, Key};
+use std::path::Path;
+
+use piston_window::{Button,EventLoop, Input, OpenGL, PistonWindow, Window, WindowSettings,Motion, Key, MouseButton};
 use opengl_graphics::GlGraphics;
-use self::resources::Resources;
+use amazing_grame::{audio, game, net, render};
+use amazing_grame::assets::Assets;
+use amazing_grame::resources::Resources;
+use amazing_grame::audio::Audio;
+use amazing_grame::profiler::{PhaseTimings, Profiler};
+use amazing_grame::point::Point;
+use amazing_grame::render::camera::CameraMode;
+use amazing_grame::render::Overlays;
+use amazing_grame::render::export::{self, ExportSettings};
+use amazing_grame::render::instant_replay::{self, Highlight, InstantReplay};
+use amazing_grame::render::particles::ParticleSystem;
+use amazing_grame::render::software::SoftwareGraphics;
+use amazing_grame::render::split::{SplitLayout, View};
+use amazing_grame::render::theme::{Theme, DEFAULT_THEME};
 use game::input::{InputController, Actions};
-use self::point::Point;
+use game::bot::Bot;
+use game::event::GameEvent;
+use game::history::History;
+use game::replay::Replay;
+use game::stats::MatchStats;
+use net::{Connection, MatchSetup, Session};
+use net::lockstep::LockstepSession;
+use net::loopback::LoopbackConfig;
+use net::rollback::RollbackSession;
+use net::spectate::{SpectatorClient, SpectatorServer};
+use self::options::Options;
 
-mod point;
-mod simulation;
-mod render;
-mod game;
-mod resources;
+mod options;
 
 const NUM_PLAYERS: usize = 2;
+const QUICKSAVE_FILE: &'static str = "quicksave.txt";
+const PROFILE_FILE: &'static str = "profile.csv";
+const SCREENSHOT_PREFIX: &'static str = "screenshot-";
+const SPECTATOR_PAN_SPEED: f64 = 15.0;
+const ZOOM_STEP: f64 = 1.25;
+/// Screen shake when a ship is swallowed by a black hole
+const SWALLOW_SHAKE: f64 = 0.6;
 
 fn main() {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, options::USAGE);
+            std::process::exit(1);
+        }
+    };
+    if options.rollback_test {
+        run_rollback_test();
+    }
+    let assets = Assets::locate(options.resources.as_ref().map(Path::new), options.pack.as_ref().map(|p| p.as_str()))
+        .unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        });
+    if let Some((ref replay, ref output)) = options.export {
+        run_export(replay, output, &assets, &options);
+        return;
+    }
+    if let Some(ref address) = options.spectate {
+        run_spectator(address, &assets, &options.theme);
+        return;
+    }
+    let mut bots = vec![];
+    for &(ship_num, difficulty) in options.bots.iter() {
+        if ship_num >= NUM_PLAYERS {
+            eprintln!("Invalid bot player {}, there are only {} players", ship_num, NUM_PLAYERS);
+            std::process::exit(1);
+        }
+        bots.push(Bot::new(ship_num, difficulty));
+    }
+
     let opengl = OpenGL::V3_2;
+    let mut window = create_window(opengl);
+    let mut gl = GlGraphics::new(opengl);
+    let mut resources: Resources = Resources::new(&assets, &options.theme).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    let connection = if let Some(port) = options.host {
+        println!("Waiting for a player to join on port {}", port);
+        let setup = MatchSetup{seed: rand::random(), arena_size: game::ARENA_SIZE, input_delay: options.input_delay};
+        Some(Connection::host(port, setup))
+    }
+    else if let Some(ref address) = options.join {
+        println!("Joining {}", address);
+        Some(Connection::join(address.as_str()))
+    }
+    else {
+        None
+    };
+    let connection = match connection {
+        Some(Err(error)) => {
+            eprintln!("Could not connect: {}", error);
+            std::process::exit(1);
+        }
+        connection => connection.map(|c| c.unwrap())
+    };
+    let mut game = match (&connection, &options.load) {
+        (&Some(ref connection), _) => game::Game::with_seed(connection.setup.arena_size, connection.setup.seed),
+        (&None, &Some(ref file)) => game::Game::load_from_file(Path::new(file)).unwrap_or_else(|error| {
+            eprintln!("Could not load {}: {}", file, error);
+            std::process::exit(1);
+        }),
+        (&None, &None) => game::Game::new(game::ARENA_SIZE)
+    };
+    let mut session: Option<Box<dyn Session>> = connection.map(|c| {
+        if options.rollback {
+            let (local_player, input_delay) = (c.local_player, c.setup.input_delay);
+            Box::new(RollbackSession::new(c, local_player, input_delay, &game)) as Box<dyn Session>
+        }
+        else {
+            Box::new(LockstepSession::new(c, game.sim.state_hash())) as Box<dyn Session>
+        }
+    });
+    let mut reported_desync = false;
+    let mut view = View::new(SplitLayout::Single, &game);
+    let mut overlays = Overlays { help: false, minimap: true, gravity: false, physics: false, profiler: false, cursor: None };
+    let mut particles = ParticleSystem::new();
+    let mut audio = Audio::open(&assets);
+    let mut profiler = Profiler::new();
+    let mut recording = options.record.as_ref().map(|_| Replay::new(&game));
+    let mut history = History::new(instant_replay::REPLAY_TICKS);
+    let mut instant_replay: Option<InstantReplay> = None;
+    let mut stats = MatchStats::new(NUM_PLAYERS);
+    let mut match_over = game.game_over;
+    // Rollback sessions simulate ticks again after mispredictions, which would count their events twice
+    let keep_stats = session.is_none() || !options.rollback;
+    let server = options.stream.map(|port| {
+        SpectatorServer::bind(port).unwrap_or_else(|error| {
+            eprintln!("Could not stream on port {}: {}", port, error);
+            std::process::exit(1);
+        })
+    });
+
+    let mut input_controller = InputController::new(NUM_PLAYERS);
+    let mut cursor = Point{x: 0.0, y: 0.0};
+
+    while let Some(e) = window.next() {
+        match e {
+            Input::Press(Button::Keyboard(key)) => {
+                match key {
+                    // Restarting on one side only would break network games
+                    Key::F8 if session.is_none() => {
+                        game = game::Game::new(game.arena_size);
+                        recording = options.record.as_ref().map(|_| Replay::new(&game));
+                        history.clear();
+                        stats = MatchStats::new(NUM_PLAYERS);
+                        match_over = false;
+                    }
+                    Key::F5 => {
+                        match game.save_to_file(Path::new(QUICKSAVE_FILE)) {
+                            Ok(()) => println!("Saved game to {}", QUICKSAVE_FILE),
+                            Err(error) => eprintln!("Could not save game: {}", error)
+                        }
+                    }
+                    Key::F9 if session.is_none() => {
+                        match game::Game::load_from_file(Path::new(QUICKSAVE_FILE)) {
+                            Ok(loaded) => {
+                                game = loaded;
+                                recording = options.record.as_ref().map(|_| Replay::new(&game));
+                                history.clear();
+                                stats = MatchStats::new(NUM_PLAYERS);
+                                match_over = game.game_over;
+                            }
+                            Err(error) => eprintln!("Could not load game: {}", error)
+                        }
+                    }
+                    // In split screen every camera belongs to a player
+                    Key::F2 if view.layout == SplitLayout::Single => {
+                        let camera = &mut view.cameras[0];
+                        camera.mode = match camera.mode {
+                            CameraMode::FollowAll => CameraMode::Follow(0),
+                            CameraMode::Follow(ship_num) if ship_num + 1 < NUM_PLAYERS => CameraMode::Follow(ship_num + 1),
+                            _ => CameraMode::FollowAll
+                        };
+                    }
+                    Key::F10 => resources.theme = resources.theme.next(),
+                    Key::F11 => view = View::new(view.layout.next(), &game),
+                    Key::F12 => {
+                        let size = window.size();
+                        match save_screenshot(&assets, &resources.theme, &game, &view, &particles, overlays, [size.width, size.height]) {
+                            Ok(path) => println!("Saved screenshot to {}", path),
+                            Err(error) => eprintln!("Could not save screenshot: {}", error)
+                        }
+                    }
+                    Key::M => overlays.minimap = !overlays.minimap,
+                    Key::F3 => overlays.gravity = !overlays.gravity,
+                    Key::F4 => overlays.physics = !overlays.physics,
+                    Key::F6 => overlays.profiler = !overlays.profiler,
+                    Key::F7 => {
+                        match profiler.write_csv(Path::new(PROFILE_FILE)) {
+                            Ok(()) => println!("Wrote timings to {}", PROFILE_FILE),
+                            Err(error) => eprintln!("Could not write timings: {}", error)
+                        }
+                    }
+                    Key::Minus => audio.change_volume(-audio::VOLUME_STEP),
+                    Key::Equals => audio.change_volume(audio::VOLUME_STEP),
+                    Key::D0 => audio.muted = !audio.muted,
+                    Key::PageUp => view.zoom_by(ZOOM_STEP),
+                    Key::PageDown => view.zoom_by(1.0 / ZOOM_STEP),
+                    Key::Home => view = View::new(view.layout, &game),
+                    Key::Space => instant_replay = None,
+                    _ => {}
+                }
+                input_controller.key_press(key);
+            }
+
+            Input::Release(Button::Keyboard(key)) => {
+                input_controller.key_release(key);
+            }
 
-    let mut settings = WindowSettings::new(
+            Input::Move(Motion::MouseCursor(x, y)) => cursor = Point{x: x, y: y},
+
+            Input::Press(Button::Mouse(MouseButton::Left)) => {
+                let size = window.size();
+                if render::is_over_help_button(&mut resources, [size.width as f64, size.height as f64], cursor) {
+                    input_controller.draw_help = !input_controller.draw_help;
+                }
+            }
+
+            Input::Update(_) => {
+                // The game waits until the replay is over
+                if let Some(mut replay) = instant_replay.take() {
+                    if replay.update(&resources.theme) {
+                        instant_replay = Some(replay);
+                    }
+                    continue;
+                }
+                let mut actions = input_controller.actions();
+                for bot in bots.iter_mut() {
+                    actions[bot.ship_num] = bot.actions(&game);
+                }
+                // Whether the game advanced by a tick
+                let mut ticked = false;
+                // Events of the ticks which can no longer change
+                let events;
+                // Only measured for local games, the sessions do not report them
+                let mut timings = PhaseTimings::default();
+                match session {
+                    Some(ref mut session) => {
+                        // The local player may use either set of keys
+                        let local = actions.iter().fold(Actions::default(), |merged, a| merged.merge(*a));
+                        match session.advance(&mut game, local) {
+                            Ok(true) => {
+                                input_controller.reset();
+                                ticked = true;
+                            }
+                            Ok(false) => {}
+                            Err(error) => {
+                                eprintln!("Network error: {}", error);
+                                break;
+                            }
+                        }
+                        if let (Some(tick), false) = (session.desync_tick(), reported_desync) {
+                            eprintln!("Desync detected at tick {}: the games of both players diverged", tick);
+                            reported_desync = true;
+                        }
+                        events = session.take_events();
+                    }
+                    None => {
+                        if let Some(ref mut recording) = recording {
+                            recording.record(&actions);
+                        }
+                        game.control(actions);
+                        timings = game.timestep();
+                        input_controller.reset();
+                        ticked = true;
+                        events = game.events.clone();
+                    }
+                }
+                profiler.record_update();
+                for event in events.iter() {
+                    if let GameEvent::ShipSwallowed { .. } = *event {
+                        view.shake(SWALLOW_SHAKE);
+                    }
+                }
+                particles.handle_events(&events, &resources.theme);
+                audio.handle_events(&events);
+                if ticked {
+                    profiler.record_tick(&game, &timings);
+                    if keep_stats && !match_over {
+                        stats.record(&game);
+                        if game.game_over {
+                            match_over = true;
+                            if let Some(ref file) = options.stats {
+                                match stats.save_to_file(Path::new(file)) {
+                                    Ok(()) => println!("Wrote match statistics to {}", file),
+                                    Err(error) => eprintln!("Could not write match statistics: {}", error)
+                                }
+                            }
+                        }
+                    }
+                    // Only local games can wait for a replay
+                    if session.is_none() && options.instant_replay {
+                        let highlight = Highlight::find(history.latest(), &game);
+                        history.push(&game);
+                        if let Some(highlight) = highlight {
+                            instant_replay = InstantReplay::new(highlight, history.states());
+                        }
+                    }
+                }
+                particles.update(&game);
+                view.update(&game);
+                if let Some(ref server) = server {
+                    server.broadcast(&game);
+                }
+            }
+
+            Input::Render(args) => {
+                overlays.help = input_controller.draw_help;
+                overlays.cursor = Some(cursor);
+                match instant_replay {
+                    Some(ref replay) => {
+                        render::render(&args, &mut gl, &mut resources, replay.game(), &replay.view, &replay.particles, Overlays { help: false, ..overlays });
+                        render::render_replay_banner(&args, &mut gl, &mut resources, replay);
+                    }
+                    None => {
+                        render::render(&args, &mut gl, &mut resources, &game, &view, &particles, overlays);
+                        if match_over {
+                            render::render_results(&args, &mut gl, &mut resources, &stats);
+                        }
+                    }
+                }
+                profiler.record_frame();
+                if overlays.profiler {
+                    render::render_profiler(&args, &mut gl, &mut resources, &profiler);
+                }
+            }
+
+            _ => {}
+        }
+    }
+    if let (Some(recording), Some(file)) = (recording, options.record.as_ref()) {
+        match recording.save_to_file(Path::new(file)) {
+            Ok(()) => println!("Saved replay of {} ticks to {}", recording.ticks.len(), file),
+            Err(error) => eprintln!("Could not save replay: {}", error)
+        }
+    }
+}
+
+fn create_window(opengl: OpenGL) -> PistonWindow {
+    let settings = WindowSettings::new(
         "Amazing Grame", [1920 as u32, 1080 as u32])
         .opengl(opengl).samples(8).fullscreen(false);
     let mut window: PistonWindow = settings.build().unwrap();
 
-    window.set_ups(60);
+    window.set_ups(game::TICK_RATE);
     window.set_max_fps(60);
+    window
+}
 
-    let mut gl = GlGraphics::new(opengl);
-    let mut resources = Resources::new();
-    let dimensions = window.output_color.get_dimensions();
-    let arena_size = Point{x: (dimensions.0 as f64), y: (dimensions.1 as f64)};
-    let mut game = game::Game::new(arena_size);
+/// Simulate a recorded match again and write it to a GIF or a directory of PNG frames
+fn run_export(replay: &str, output: &str, assets: &Assets, options: &Options) {
+    let result = Replay::load_from_file(Path::new(replay)).and_then(|replay| {
+        let mut resources: Resources<SoftwareGraphics> = Resources::new(assets, &options.theme)?;
+        let settings = ExportSettings {
+            width: options.frame_size[0],
+            height: options.frame_size[1],
+            step: options.frame_step,
+            overlays: Overlays { help: false, minimap: true, gravity: false, physics: false, profiler: false, cursor: None }
+        };
+        export::export_replay(&replay, &mut resources, Path::new(output), &settings)
+    });
+    match result {
+        Ok(frames) => println!("Exported {} frames to {}", frames, output),
+        Err(error) => {
+            eprintln!("Could not export {}: {}", replay, error);
+            std::process::exit(1);
+        }
+    }
+}
 
-    let mut input_controller = InputController::new(NUM_PLAYERS);
+/// Render the game without OpenGL and save it as the next free `screenshot-N.png`
+fn save_screenshot(assets: &Assets, theme: &Theme, game: &game::Game, view: &View, particles: &ParticleSystem, overlays: Overlays, size: [u32; 2]) -> Result<String, String> {
+    let mut resources: Resources<SoftwareGraphics> = Resources::new(assets, DEFAULT_THEME)?;
+    resources.theme = theme.clone();
+    let image = render::render_to_image(&mut resources, game, view, particles, overlays, size[0], size[1]);
+    let path = (1..).map(|i| format!("{}{}.png", SCREENSHOT_PREFIX, i)).find(|path| !Path::new(path).exists()).unwrap();
+    image.save(&path).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Watch a game streamed by another process. Arrow keys and page up/down move the camera,
+/// the number keys follow a player, A follows all players, F frees the camera and Home resets it.
+/// M toggles the minimap, G the gravity field and F10 cycles the color themes, as in the game.
+fn run_spectator(address: &str, assets: &Assets, theme: &str) {
+    let exit = |error: String| -> ! {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    };
+    let client = SpectatorClient::connect(address)
+        .unwrap_or_else(|error| exit(format!("Could not connect to {}: {}", address, error)));
+    println!("Waiting for the match to start");
+    let mut game = client.wait().unwrap_or_else(|error| exit(error));
+
+    let opengl = OpenGL::V3_2;
+    let mut window = create_window(opengl);
+    let mut gl = GlGraphics::new(opengl);
+    let mut resources: Resources = Resources::new(assets, theme).unwrap_or_else(|error| exit(error));
+    let mut view = View::new(SplitLayout::Single, &game);
+    let mut overlays = Overlays { help: false, minimap: true, gravity: false, physics: false, profiler: false, cursor: None };
+    let mut particles = ParticleSystem::new();
+    let mut pan = Point{x: 0.0, y: 0.0};
 
     while let Some(e) = window.next() {
         match e {
             Input::Press(Button::Keyboard(key)) => {
                 match key {
-                    Key::F8 => {
-                        let dimensions = window.output_color.get_dimensions();
-                        let arena_size = Point{x: (dimensions.0 as f64), y: (dimensions.1 as f64)};
-                        game = game::Game::new(arena_size);
-                    }
+                    Key::Left => pan.x = -SPECTATOR_PAN_SPEED,
+                    Key::Right => pan.x = SPECTATOR_PAN_SPEED,
+                    Key::Up => pan.y = -SPECTATOR_PAN_SPEED,
+                    Key::Down => pan.y = SPECTATOR_PAN_SPEED,
+                    Key::PageUp => view.zoom_by(ZOOM_STEP),
+                    Key::PageDown => view.zoom_by(1.0 / ZOOM_STEP),
+                    Key::D1 => view.cameras[0].mode = CameraMode::Follow(0),
+                    Key::D2 => view.cameras[0].mode = CameraMode::Follow(1),
+                    Key::A => view.cameras[0].mode = CameraMode::FollowAll,
+                    Key::F => view.cameras[0].mode = CameraMode::Free,
+                    Key::M => overlays.minimap = !overlays.minimap,
+                    Key::G => overlays.gravity = !overlays.gravity,
+                    Key::F10 => resources.theme = resources.theme.next(),
+                    Key::Home => view = View::new(SplitLayout::Single, &game),
                     _ => {}
                 }
-                input_controller.key_press(key);
             }
 
             Input::Release(Button::Keyboard(key)) => {
-                input_controller.key_release(key);
+                match key {
+                    Key::Left | Key::Right => pan.x = 0.0,
+                    Key::Up | Key::Down => pan.y = 0.0,
+                    _ => {}
+                }
             }
 
             Input::Update(_) => {
-                game.control(input_controller.actions());
-                game.timestep();
-                input_controller.reset();
+                match client.latest() {
+                    Ok(Some(latest)) => game = latest,
+                    Ok(None) => {}
+                    Err(error) => {
+                        println!("{}", error);
+                        break;
+                    }
+                }
+                if pan.norm() > 0.0 {
+                    view.cameras[0].pan(pan);
+                }
+                particles.update(&game);
+                view.update(&game);
             }
 
             Input::Render(args) => {
-                gl.draw(args.viewport(), |context, gl| render::render(context, gl, &mut resources, &game, input_controller.draw_help));
+                render::render(&args, &mut gl, &mut resources, &game, &view, &particles, overlays);
             }
 
             _ => {}
         }
     }
 }
+
+/// Play matches between two rollback sessions over simulated bad networks and check that they
+/// always end up in the same state as a local game
+fn run_rollback_test() -> ! {
+    let scenarios = [(0, 0, 0.0), (5, 0, 0.1), (12, 4, 0.3), (3, 10, 0.5)];
+    let mut success = true;
+    for (i, &(latency, jitter, loss)) in scenarios.iter().enumerate() {
+        let report = net::loopback::run(LoopbackConfig {
+            latency: latency,
+            jitter: jitter,
+            loss: loss,
+            input_delay: 2,
+            frames: 1500,
+            seed: i as u64
+        });
+        println!("latency {:2} jitter {:2} loss {:3.0}%: {} ({} rollbacks, max depth {}, {} stalls)",
+                 latency, jitter, loss * 100.0,
+                 if !report.converged { "DIVERGED" } else if !report.events_match { "converged, but with wrong events" } else { "converged" },
+                 report.stats[0].rollbacks + report.stats[1].rollbacks,
+                 report.stats[0].max_rollback_depth.max(report.stats[1].max_rollback_depth),
+                 report.stats[0].stalls + report.stats[1].stalls);
+        success &= report.converged && report.events_match && report.desync_tick.is_none();
+    }
+    std::process::exit(if success { 0 } else { 1 });
+}
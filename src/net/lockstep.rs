@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::io;
+
+use game::Game;
+use game::event::GameEvent;
+use game::input::Actions;
+use net::{unacknowledged_inputs, Connection, Packet, Session, Transport};
+
+/// Number of ticks for which state hashes are kept around for comparison
+const HASH_HISTORY: u32 = 600;
+
+/// Lockstep synchronization: a tick is only simulated once the inputs of both players for it are known.
+/// Local inputs are scheduled `input_delay` ticks into the future, which hides the latency as long as
+/// it stays below the delay.
+pub struct LockstepSession {
+    pub connection: Connection,
+    /// The next tick to be simulated
+    tick: u32,
+    local_inputs: Vec<Actions>,
+    /// Tick of the first of `local_inputs`, the ones before were simulated and reached the peer
+    first_input_tick: u32,
+    remote_inputs: HashMap<u32, Actions>,
+    local_hashes: HashMap<u32, u64>,
+    remote_hashes: HashMap<u32, u64>,
+    /// The tick the peer is at, so it has all our inputs before it
+    remote_tick: u32,
+    /// Tick at which the hashes were last pruned
+    hashes_pruned: u32,
+    /// Events of the simulated ticks, until they are taken
+    events: Vec<GameEvent>,
+    /// First tick at which the state hashes of the two peers differed
+    pub desync_tick: Option<u32>
+}
+
+impl LockstepSession {
+    /// `initial_hash` is the state hash of the freshly created game
+    pub fn new(connection: Connection, initial_hash: u64) -> LockstepSession {
+        let input_delay = connection.setup.input_delay as usize;
+        let mut local_hashes = HashMap::new();
+        local_hashes.insert(0, initial_hash);
+        LockstepSession {
+            connection: connection,
+            tick: 0,
+            local_inputs: vec![Actions::default(); input_delay],
+            first_input_tick: 0,
+            remote_inputs: HashMap::new(),
+            local_hashes: local_hashes,
+            remote_hashes: HashMap::new(),
+            remote_tick: 0,
+            hashes_pruned: 0,
+            events: vec![],
+            desync_tick: None
+        }
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Exchange inputs with the peer. Returns the actions of all players for the next tick,
+    /// or `None` if the game has to wait for the peer.
+    pub fn next_inputs(&mut self, local: Actions) -> io::Result<Option<Vec<Actions>>> {
+        let target = self.tick as usize + self.connection.setup.input_delay as usize;
+        if self.first_input_tick as usize + self.local_inputs.len() <= target {
+            self.local_inputs.push(local);
+        }
+        for packet in self.connection.receive()? {
+            if let Packet::Input { first_tick, actions, hash_tick, hash } = packet {
+                for (i, actions) in actions.into_iter().enumerate() {
+                    let tick = first_tick + i as u32;
+                    if tick >= self.tick {
+                        self.remote_inputs.insert(tick, actions);
+                    }
+                }
+                self.remote_tick = self.remote_tick.max(hash_tick);
+                self.remote_hashes.insert(hash_tick, hash);
+                self.check_hash(hash_tick);
+            }
+        }
+        self.drop_old_inputs();
+        self.send_inputs()?;
+        let remote = match self.remote_inputs.remove(&self.tick) {
+            Some(remote) => remote,
+            None => return Ok(None)
+        };
+        let mut inputs = vec![Actions::default(); 2];
+        inputs[self.connection.local_player] = self.local_inputs[(self.tick - self.first_input_tick) as usize];
+        inputs[self.connection.remote_player()] = remote;
+        self.tick += 1;
+        Ok(Some(inputs))
+    }
+
+    /// Record the state hash after simulating the inputs returned by `next_inputs`
+    pub fn record_hash(&mut self, hash: u64) {
+        let tick = self.tick;
+        self.local_hashes.insert(tick, hash);
+        self.check_hash(tick);
+        if tick >= self.hashes_pruned + HASH_HISTORY {
+            self.local_hashes.retain(|&t, _| t + HASH_HISTORY >= tick);
+            self.remote_hashes.retain(|&t, _| t + HASH_HISTORY >= tick);
+            self.hashes_pruned = tick;
+        }
+    }
+
+    /// Forget the local inputs which were simulated and which the peer has
+    fn drop_old_inputs(&mut self) {
+        let keep = self.tick.min(self.remote_tick).max(self.first_input_tick);
+        self.local_inputs.drain(..(keep - self.first_input_tick) as usize);
+        self.first_input_tick = keep;
+    }
+
+    fn check_hash(&mut self, tick: u32) {
+        if let (Some(local), Some(remote)) = (self.local_hashes.get(&tick), self.remote_hashes.get(&tick)) {
+            if local != remote && self.desync_tick.is_none() {
+                self.desync_tick = Some(tick);
+            }
+        }
+    }
+
+    fn send_inputs(&mut self) -> io::Result<()> {
+        // Everything the peer may still be missing, so no input is lost for good
+        let (first_tick, actions) = unacknowledged_inputs(&self.local_inputs, self.first_input_tick, self.remote_tick);
+        let packet = Packet::Input {
+            first_tick: first_tick,
+            actions: actions.to_vec(),
+            hash_tick: self.tick,
+            hash: self.local_hashes.get(&self.tick).cloned().unwrap_or(0)
+        };
+        self.connection.send(&packet)
+    }
+}
+
+impl Session for LockstepSession {
+    fn advance(&mut self, game: &mut Game, local: Actions) -> io::Result<bool> {
+        match self.next_inputs(local)? {
+            Some(inputs) => {
+                game.control(inputs);
+                game.timestep();
+                self.record_hash(game.sim.state_hash());
+                self.events.extend(game.events.iter().cloned());
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    fn desync_tick(&self) -> Option<u32> {
+        self.desync_tick
+    }
+
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain(..).collect()
+    }
+}
@@ -0,0 +1,199 @@
+//! In-memory network with artificial latency and packet loss, to check that two rollback
+//! sessions end up with the same game as a plain local simulation.
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+use rand::Rng;
+
+use game::{Game, ARENA_SIZE};
+use game::input::Actions;
+use game::rng::GameRng;
+use net::{Packet, Session, Transport};
+use net::rollback::{RollbackSession, RollbackStats};
+
+/// Number of frames for which each scripted input is held
+const SCRIPT_HOLD_FRAMES: u32 = 8;
+
+/// Give up if the sessions did not agree on all inputs after this many additional ticks
+const MAX_EXTRA_TICKS: u32 = 10000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LoopbackConfig {
+    /// Delivery delay of every packet, in ticks
+    pub latency: u32,
+    /// Random additional delay of up to this many ticks, which also reorders packets
+    pub jitter: u32,
+    /// Probability of a packet being dropped
+    pub loss: f64,
+    pub input_delay: u32,
+    pub frames: u32,
+    pub seed: u64
+}
+
+struct Network {
+    now: u32,
+    /// Packets in flight towards each side, with their arrival time
+    queues: [Vec<(u32, Vec<u8>)>; 2],
+    config: LoopbackConfig,
+    rng: GameRng
+}
+
+/// One end of the in-memory network
+pub struct LoopbackTransport {
+    network: Rc<RefCell<Network>>,
+    side: usize
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, packet: &Packet) -> io::Result<()> {
+        let mut network = self.network.borrow_mut();
+        let config = network.config;
+        if network.rng.gen::<f64>() < config.loss {
+            return Ok(());
+        }
+        let delay = config.latency + network.rng.gen_range(0, config.jitter + 1);
+        let arrival = network.now + delay;
+        network.queues[1 - self.side].push((arrival, packet.encode()));
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<Packet>> {
+        let mut network = self.network.borrow_mut();
+        let now = network.now;
+        let queue = &mut network.queues[self.side];
+        let packets = queue.iter()
+            .filter(|&&(arrival, _)| arrival <= now)
+            .filter_map(|&(_, ref data)| Packet::decode(data))
+            .collect();
+        queue.retain(|&(arrival, _)| arrival > now);
+        Ok(packets)
+    }
+}
+
+#[derive(Debug)]
+pub struct LoopbackReport {
+    pub config: LoopbackConfig,
+    /// Whether both sessions ended in exactly the state of the local reference simulation
+    pub converged: bool,
+    /// Whether both sessions reported the events of the reference simulation, each of them once
+    pub events_match: bool,
+    pub desync_tick: Option<u32>,
+    pub stats: [RollbackStats; 2]
+}
+
+/// Deterministic, randomly changing inputs for a player
+fn scripted_input(seed: u64, player: usize, frame: u32) -> Actions {
+    let mut rng = GameRng::new(seed ^ (player as u64) << 32 ^ (frame / SCRIPT_HOLD_FRAMES) as u64);
+    let mut actions = Actions::from_bits(rng.gen::<u8>());
+    actions.respawn = rng.gen_weighted_bool(20);
+    actions
+}
+
+/// The input of `player` in `frame`, which the session scheduled `input_delay` frames before
+fn scheduled_input(config: &LoopbackConfig, player: usize, frame: u32) -> Actions {
+    if frame < config.input_delay {
+        Actions::default()
+    }
+    else {
+        scripted_input(config.seed, player, frame - config.input_delay)
+    }
+}
+
+/// Play `config.frames` frames with two rollback sessions connected through a lossy loopback network
+/// and compare the result to a game simulated locally with the inputs of both players.
+pub fn run(config: LoopbackConfig) -> LoopbackReport {
+    let network = Rc::new(RefCell::new(Network {
+        now: 0,
+        queues: [vec![], vec![]],
+        config: config,
+        rng: GameRng::new(config.seed)
+    }));
+    let mut games = [Game::with_seed(ARENA_SIZE, config.seed), Game::with_seed(ARENA_SIZE, config.seed)];
+    let mut sessions: Vec<_> = (0..2).map(|player| {
+        let transport = LoopbackTransport { network: network.clone(), side: player };
+        RollbackSession::new(transport, player, config.input_delay, &games[player])
+    }).collect();
+
+    let mut desync_tick = None;
+    let mut events = [vec![], vec![]];
+    let mut ticks = 0;
+    while sessions.iter().any(|s| s.confirmed_hash(config.frames).is_none()) && ticks < config.frames + MAX_EXTRA_TICKS {
+        for (player, session) in sessions.iter_mut().enumerate() {
+            let input = scripted_input(config.seed, player, session.frame());
+            if session.advance(&mut games[player], input).is_err() {
+                break;
+            }
+            desync_tick = desync_tick.or(session.desync_tick());
+            events[player].extend(session.take_events().iter().map(|event| format!("{:?}", event)));
+        }
+        network.borrow_mut().now += 1;
+        ticks += 1;
+    }
+
+    let mut reference = Game::with_seed(ARENA_SIZE, config.seed);
+    let mut expected_events = vec![];
+    for frame in 0..config.frames {
+        reference.control((0..2).map(|player| scheduled_input(&config, player, frame)).collect());
+        reference.timestep();
+        expected_events.extend(reference.events.iter().map(|event| format!("{:?}", event)));
+    }
+    let expected = reference.sim.state_hash();
+    let converged = sessions.iter().all(|s| s.confirmed_hash(config.frames) == Some(expected));
+    // The sessions may have confirmed a few frames more than the reference simulated
+    let events_match = events.iter().all(|events| events.starts_with(&expected_events));
+    LoopbackReport {
+        config: config,
+        converged: converged,
+        events_match: events_match,
+        desync_tick: desync_tick,
+        stats: [sessions[0].stats, sessions[1].stats]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_converges(latency: u32, jitter: u32, loss: f64, input_delay: u32) {
+        for seed in 0..2 {
+            let report = run(LoopbackConfig {
+                latency: latency,
+                jitter: jitter,
+                loss: loss,
+                input_delay: input_delay,
+                frames: 600,
+                seed: seed
+            });
+            assert!(report.converged, "{:?}", report);
+            assert!(report.events_match, "{:?}", report);
+            assert_eq!(report.desync_tick, None);
+        }
+    }
+
+    #[test]
+    fn perfect_network() {
+        assert_converges(0, 0, 0.0, 2);
+    }
+
+    #[test]
+    fn latency_with_some_loss() {
+        assert_converges(5, 0, 0.1, 2);
+    }
+
+    #[test]
+    fn jitter_with_heavy_loss() {
+        assert_converges(12, 4, 0.3, 2);
+    }
+
+    #[test]
+    fn reordering_with_extreme_loss() {
+        assert_converges(3, 10, 0.5, 2);
+    }
+
+    #[test]
+    fn input_delay_longer_than_redundancy() {
+        assert_converges(3, 2, 0.3, 20);
+    }
+}
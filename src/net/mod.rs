@@ -0,0 +1,330 @@
+//! Network play over UDP. Both peers run the same deterministic `Game` and only exchange
+//! the `Actions` of their player for every tick.
+
+pub mod lockstep;
+pub mod loopback;
+pub mod rollback;
+pub mod spectate;
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use ::point::Point;
+use game::Game;
+use game::event::GameEvent;
+use game::input::Actions;
+
+pub const DEFAULT_INPUT_DELAY: u32 = 3;
+/// Largest input delay for which all inputs the peer may still be missing fit into one packet
+pub const MAX_INPUT_DELAY: u32 = 120;
+
+/// Minimum number of past ticks whose inputs are repeated in every packet, to survive packet loss
+pub const INPUT_REDUNDANCY: usize = 8;
+/// Maximum number of inputs in a single packet
+const MAX_INPUTS_PER_PACKET: usize = 255;
+
+const MAX_PACKET_SIZE: usize = 1024;
+const HANDSHAKE_RESEND_INTERVAL: u64 = 200;
+const JOIN_TIMEOUT: u64 = 10;
+const DISCONNECT_TIMEOUT: u64 = 10;
+
+const HELLO: u8 = 0;
+const WELCOME: u8 = 1;
+const INPUT: u8 = 2;
+
+/// A way of synchronizing a local `Game` with the one of the peer
+pub trait Session {
+    /// Advance `game` by one tick, if possible. Returns whether the game advanced.
+    fn advance(&mut self, game: &mut Game, local: Actions) -> io::Result<bool>;
+
+    /// First tick at which the state hashes of the two peers differed
+    fn desync_tick(&self) -> Option<u32>;
+
+    /// Events of the ticks which became final since the last call. Unlike `Game::events`, they
+    /// are never undone or repeated by a rollback.
+    fn take_events(&mut self) -> Vec<GameEvent>;
+}
+
+/// Unreliable, unordered delivery of packets to the peer
+pub trait Transport {
+    fn send(&mut self, packet: &Packet) -> io::Result<()>;
+
+    /// All packets that arrived since the last call
+    fn receive(&mut self) -> io::Result<Vec<Packet>>;
+}
+
+/// The inputs to send to a peer which has acknowledged all inputs before `acknowledged`.
+/// `inputs` are those from tick `first_tick` on, the older ones were dropped.
+pub fn unacknowledged_inputs(inputs: &[Actions], first_tick: u32, acknowledged: u32) -> (u32, &[Actions]) {
+    let first = first_tick as usize;
+    let end = first + inputs.len();
+    let start = (acknowledged as usize)
+        .min(end.saturating_sub(INPUT_REDUNDANCY))
+        .max(end.saturating_sub(MAX_INPUTS_PER_PACKET))
+        .max(first);
+    (start as u32, &inputs[start - first..])
+}
+
+/// Everything both peers need to agree on to start the same game
+#[derive(Clone, Copy, Debug)]
+pub struct MatchSetup {
+    pub seed: u64,
+    pub arena_size: Point,
+    pub input_delay: u32
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Hello,
+    Welcome { seed: u64, arena_size: (f64, f64), input_delay: u32 },
+    /// Inputs of the sender for the ticks `first_tick..first_tick + actions.len()`
+    /// and the sender's state hash at the start of `hash_tick`
+    Input { first_tick: u32, actions: Vec<Actions>, hash_tick: u32, hash: u64 }
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![];
+        match *self {
+            Packet::Hello => data.push(HELLO),
+            Packet::Welcome { seed, arena_size, input_delay } => {
+                data.push(WELCOME);
+                data.extend_from_slice(&seed.to_le_bytes());
+                data.extend_from_slice(&arena_size.0.to_bits().to_le_bytes());
+                data.extend_from_slice(&arena_size.1.to_bits().to_le_bytes());
+                data.extend_from_slice(&input_delay.to_le_bytes());
+            }
+            Packet::Input { first_tick, ref actions, hash_tick, hash } => {
+                data.push(INPUT);
+                data.extend_from_slice(&first_tick.to_le_bytes());
+                data.push(actions.len() as u8);
+                data.extend(actions.iter().map(|a| a.to_bits()));
+                data.extend_from_slice(&hash_tick.to_le_bytes());
+                data.extend_from_slice(&hash.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    /// Returns `None` for malformed packets
+    pub fn decode(data: &[u8]) -> Option<Packet> {
+        let mut reader = Reader { data: data };
+        let packet = match reader.u8()? {
+            HELLO => Packet::Hello,
+            WELCOME => Packet::Welcome {
+                seed: reader.u64()?,
+                arena_size: (f64::from_bits(reader.u64()?), f64::from_bits(reader.u64()?)),
+                input_delay: reader.u32()?
+            },
+            INPUT => {
+                let first_tick = reader.u32()?;
+                let count = reader.u8()? as usize;
+                let mut actions = vec![];
+                for _ in 0..count {
+                    actions.push(Actions::from_bits(reader.u8()?));
+                }
+                Packet::Input {
+                    first_tick: first_tick,
+                    actions: actions,
+                    hash_tick: reader.u32()?,
+                    hash: reader.u64()?
+                }
+            }
+            _ => return None
+        };
+        Some(packet)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.data.len() < length {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(length);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|bytes| {
+            let mut array = [0; 4];
+            array.copy_from_slice(bytes);
+            u32::from_le_bytes(array)
+        })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|bytes| {
+            let mut array = [0; 8];
+            array.copy_from_slice(bytes);
+            u64::from_le_bytes(array)
+        })
+    }
+}
+
+/// A UDP socket talking to exactly one peer
+pub struct Connection {
+    pub socket: UdpSocket,
+    pub peer: SocketAddr,
+    /// Player slot controlled by this side of the connection
+    pub local_player: usize,
+    pub setup: MatchSetup,
+    last_received: Instant
+}
+
+impl Connection {
+    /// Wait for a player to join on `port`. The host controls player 0.
+    pub fn host(port: u16, setup: MatchSetup) -> io::Result<Connection> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            let (size, peer) = socket.recv_from(&mut buffer)?;
+            if Packet::decode(&buffer[..size]) == Some(Packet::Hello) {
+                let connection = Connection {
+                    socket: socket,
+                    peer: peer,
+                    local_player: 0,
+                    setup: setup,
+                    last_received: Instant::now()
+                };
+                connection.send_welcome()?;
+                connection.socket.set_nonblocking(true)?;
+                return Ok(connection);
+            }
+        }
+    }
+
+    /// Join a game hosted at `address`. The joining side controls player 1.
+    pub fn join<A: ToSocketAddrs>(address: A) -> io::Result<Connection> {
+        let peer = address.to_socket_addrs()?.next()
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Invalid address"))?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_read_timeout(Some(Duration::from_millis(HANDSHAKE_RESEND_INTERVAL)))?;
+        let start = Instant::now();
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        while start.elapsed() < Duration::from_secs(JOIN_TIMEOUT) {
+            socket.send_to(&Packet::Hello.encode(), peer)?;
+            let size = match socket.recv_from(&mut buffer) {
+                Ok((size, sender)) if sender == peer => size,
+                Ok(_) => continue,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e)
+            };
+            if let Some(Packet::Welcome { seed, arena_size, input_delay }) = Packet::decode(&buffer[..size]) {
+                socket.set_nonblocking(true)?;
+                return Ok(Connection {
+                    socket: socket,
+                    peer: peer,
+                    local_player: 1,
+                    setup: MatchSetup {
+                        seed: seed,
+                        arena_size: Point{x: arena_size.0, y: arena_size.1},
+                        input_delay: input_delay
+                    },
+                    last_received: Instant::now()
+                });
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, "No answer from host"))
+    }
+
+    pub fn remote_player(&self) -> usize {
+        1 - self.local_player
+    }
+
+    fn send_welcome(&self) -> io::Result<()> {
+        self.send_packet(&Packet::Welcome {
+            seed: self.setup.seed,
+            arena_size: (self.setup.arena_size.x, self.setup.arena_size.y),
+            input_delay: self.setup.input_delay
+        })
+    }
+
+    fn send_packet(&self, packet: &Packet) -> io::Result<()> {
+        self.socket.send_to(&packet.encode(), self.peer).map(|_| ())
+    }
+}
+
+impl Transport for Connection {
+    fn send(&mut self, packet: &Packet) -> io::Result<()> {
+        self.send_packet(packet)
+    }
+
+    /// Answers repeated handshakes on the way, in case our welcome got lost.
+    fn receive(&mut self) -> io::Result<Vec<Packet>> {
+        let mut packets = vec![];
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((size, sender)) => {
+                    if sender != self.peer {
+                        continue;
+                    }
+                    self.last_received = Instant::now();
+                    match Packet::decode(&buffer[..size]) {
+                        Some(Packet::Hello) => self.send_welcome()?,
+                        Some(packet) => packets.push(packet),
+                        None => {}
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e)
+            }
+        }
+        if self.last_received.elapsed() > Duration::from_secs(DISCONNECT_TIMEOUT) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Lost connection to peer"));
+        }
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_are_resent_until_acknowledged() {
+        let inputs: Vec<Actions> = (0..40).map(|i| Actions::from_bits(i as u8 % 32)).collect();
+        let (first_tick, actions) = unacknowledged_inputs(&inputs, 0, 0);
+        assert_eq!(first_tick, 0);
+        assert_eq!(actions, &inputs[..]);
+        // Recent inputs are repeated even when acknowledged
+        let (first_tick, actions) = unacknowledged_inputs(&inputs, 0, 39);
+        assert_eq!(first_tick, 40 - INPUT_REDUNDANCY as u32);
+        assert_eq!(actions.len(), INPUT_REDUNDANCY);
+    }
+
+    #[test]
+    fn dropped_inputs_are_not_resent() {
+        let inputs: Vec<Actions> = (0..40).map(|i| Actions::from_bits(i as u8 % 32)).collect();
+        // Only the inputs from tick 135 on are left, the peer has those before 90 and may miss the rest
+        let (first_tick, actions) = unacknowledged_inputs(&inputs[35..], 135, 90);
+        assert_eq!(first_tick, 135);
+        assert_eq!(actions, &inputs[35..]);
+        let (first_tick, actions) = unacknowledged_inputs(&inputs[10..], 110, 120);
+        assert_eq!(first_tick, 120);
+        assert_eq!(actions, &inputs[20..]);
+    }
+
+    #[test]
+    fn unacknowledged_inputs_of_the_largest_delay_fit_into_a_packet() {
+        // The peers are at most one input delay apart, in either direction
+        let window = 2 * MAX_INPUT_DELAY as usize + 2;
+        let inputs = vec![Actions::default(); window];
+        let (first_tick, actions) = unacknowledged_inputs(&inputs, 0, 0);
+        assert_eq!((first_tick, actions.len()), (0, window));
+        let packet = Packet::Input { first_tick: 0, actions: actions.to_vec(), hash_tick: 0, hash: 0 };
+        assert!(packet.encode().len() <= MAX_PACKET_SIZE);
+        assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use game::Game;
+use game::event::GameEvent;
+use game::input::Actions;
+use net::{unacknowledged_inputs, Packet, Session, Transport};
+
+/// Number of frames the game may run ahead of the last confirmed remote input before it waits
+pub const MAX_PREDICTION: u32 = 10;
+
+/// Number of frames for which state hashes are kept around for comparison
+const HASH_HISTORY: u32 = 600;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RollbackStats {
+    pub rollbacks: usize,
+    pub resimulated_frames: usize,
+    pub max_rollback_depth: u32,
+    pub stalls: usize
+}
+
+/// GGPO-style rollback synchronization: the game never waits for remote inputs but predicts them
+/// by repeating the last known ones. When an input arrives that differs from the prediction,
+/// the game is restored to the snapshot of that frame and simulated forward again.
+pub struct RollbackSession<T: Transport> {
+    pub transport: T,
+    pub local_player: usize,
+    input_delay: u32,
+    /// The next frame to be simulated
+    frame: u32,
+    /// Frame of the first of the inputs below, the ones before are no longer needed
+    first_input_frame: u32,
+    local_inputs: Vec<Actions>,
+    remote_inputs: Vec<Option<Actions>>,
+    /// The remote input (confirmed or predicted) each simulated frame was run with
+    used_remote_inputs: Vec<Actions>,
+    /// Number of leading frames for which the remote input is known
+    confirmed: u32,
+    /// Game state at the start of every frame from `snapshot_start` on
+    snapshots: VecDeque<Game>,
+    snapshot_start: u32,
+    /// The last frame whose (final) state has been hashed
+    hashed: u32,
+    local_hashes: HashMap<u32, u64>,
+    remote_hashes: HashMap<u32, u64>,
+    /// Frame at which the hashes were last pruned
+    hashes_pruned: u32,
+    /// Events of the frames which became final, until they are taken
+    events: Vec<GameEvent>,
+    /// All frames before this one are confirmed by the peer
+    remote_confirmed: u32,
+    pub desync_tick: Option<u32>,
+    pub stats: RollbackStats
+}
+
+impl<T: Transport> RollbackSession<T> {
+    pub fn new(transport: T, local_player: usize, input_delay: u32, game: &Game) -> RollbackSession<T> {
+        let mut local_hashes = HashMap::new();
+        local_hashes.insert(0, game.sim.state_hash());
+        RollbackSession {
+            transport: transport,
+            local_player: local_player,
+            input_delay: input_delay,
+            frame: 0,
+            first_input_frame: 0,
+            local_inputs: vec![Actions::default(); input_delay as usize],
+            remote_inputs: vec![],
+            used_remote_inputs: vec![],
+            confirmed: 0,
+            snapshots: VecDeque::new(),
+            snapshot_start: 0,
+            hashed: 0,
+            local_hashes: local_hashes,
+            remote_hashes: HashMap::new(),
+            hashes_pruned: 0,
+            events: vec![],
+            remote_confirmed: 0,
+            desync_tick: None,
+            stats: RollbackStats::default()
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Number of leading frames for which the inputs of both players are known
+    pub fn confirmed_frame(&self) -> u32 {
+        self.confirmed
+    }
+
+    /// State hash at the start of `frame`, once all inputs before it are confirmed
+    pub fn confirmed_hash(&self, frame: u32) -> Option<u64> {
+        self.local_hashes.get(&frame).cloned()
+    }
+
+    fn receive(&mut self) -> io::Result<Option<u32>> {
+        let mut rollback_frame: Option<u32> = None;
+        for packet in self.transport.receive()? {
+            if let Packet::Input { first_tick, actions, hash_tick, hash } = packet {
+                for (i, actions) in actions.into_iter().enumerate() {
+                    let frame = first_tick + i as u32;
+                    if self.set_remote_input(frame, actions) {
+                        rollback_frame = Some(rollback_frame.map_or(frame, |f| f.min(frame)));
+                    }
+                }
+                self.remote_confirmed = self.remote_confirmed.max(hash_tick);
+                self.remote_hashes.insert(hash_tick, hash);
+                self.check_hash(hash_tick);
+            }
+        }
+        while let Some(&Some(_)) = self.remote_inputs.get((self.confirmed - self.first_input_frame) as usize) {
+            self.confirmed += 1;
+        }
+        Ok(rollback_frame)
+    }
+
+    /// Store a remote input, returns whether it contradicts the prediction for an already simulated frame
+    fn set_remote_input(&mut self, frame: u32, actions: Actions) -> bool {
+        // Dropped inputs were all confirmed
+        if frame < self.first_input_frame {
+            return false;
+        }
+        let index = (frame - self.first_input_frame) as usize;
+        if self.remote_inputs.len() <= index {
+            self.remote_inputs.resize(index + 1, None);
+        }
+        if self.remote_inputs[index].is_some() {
+            return false;
+        }
+        self.remote_inputs[index] = Some(actions);
+        frame < self.frame && self.used_remote_inputs[index] != actions
+    }
+
+    /// Repeat the last known input of the peer
+    fn predict_remote_input(&self, frame: u32) -> Actions {
+        let end = ((frame - self.first_input_frame) as usize).min(self.remote_inputs.len());
+        let mut prediction = self.remote_inputs[..end].iter().rev()
+            .filter_map(|a| *a)
+            .next()
+            .unwrap_or_default();
+        // Pressing respawn again is unlikely
+        prediction.respawn = false;
+        prediction
+    }
+
+    fn simulate_frame(&mut self, game: &mut Game, frame: u32) {
+        let index = (frame - self.snapshot_start) as usize;
+        if index < self.snapshots.len() {
+            self.snapshots[index] = game.clone();
+        }
+        else {
+            self.snapshots.push_back(game.clone());
+        }
+        let input_index = (frame - self.first_input_frame) as usize;
+        let remote = match self.remote_inputs.get(input_index) {
+            Some(&Some(actions)) => actions,
+            _ => self.predict_remote_input(frame)
+        };
+        if input_index < self.used_remote_inputs.len() {
+            self.used_remote_inputs[input_index] = remote;
+        }
+        else {
+            self.used_remote_inputs.push(remote);
+        }
+        let mut inputs = vec![Actions::default(); 2];
+        inputs[self.local_player] = self.local_inputs[input_index];
+        inputs[1 - self.local_player] = remote;
+        game.control(inputs);
+        game.timestep();
+    }
+
+    fn rollback(&mut self, game: &mut Game, frame: u32) {
+        let depth = self.frame - frame;
+        self.stats.rollbacks += 1;
+        self.stats.resimulated_frames += depth as usize;
+        self.stats.max_rollback_depth = self.stats.max_rollback_depth.max(depth);
+        *game = self.snapshots[(frame - self.snapshot_start) as usize].clone();
+        for f in frame..self.frame {
+            self.simulate_frame(game, f);
+        }
+    }
+
+    /// Hash all states which can not change anymore, keep the events that led to them and
+    /// drop the snapshots and inputs which are no longer needed
+    fn confirm_states(&mut self, game: &Game) {
+        let last_final = self.confirmed.min(self.frame);
+        while self.hashed < last_final {
+            self.hashed += 1;
+            // The state at the start of a frame holds the events of the frame before
+            let state = if self.hashed == self.frame {
+                game
+            }
+            else {
+                &self.snapshots[(self.hashed - self.snapshot_start) as usize]
+            };
+            self.local_hashes.insert(self.hashed, state.sim.state_hash());
+            self.events.extend(state.events.iter().cloned());
+            self.check_hash(self.hashed);
+        }
+        while self.snapshot_start < last_final {
+            self.snapshots.pop_front();
+            self.snapshot_start += 1;
+        }
+        if self.hashed >= self.hashes_pruned + HASH_HISTORY {
+            let hashed = self.hashed;
+            self.local_hashes.retain(|&f, _| f + HASH_HISTORY >= hashed);
+            self.remote_hashes.retain(|&f, _| f + HASH_HISTORY >= hashed);
+            self.hashes_pruned = hashed;
+        }
+        self.drop_old_inputs();
+    }
+
+    /// Forget the inputs of frames which can't be simulated again and which the peer has. The last
+    /// confirmed remote input stays, to predict the next ones.
+    fn drop_old_inputs(&mut self) {
+        let keep = self.snapshot_start
+            .min(self.remote_confirmed)
+            .min(self.confirmed.saturating_sub(1))
+            .max(self.first_input_frame);
+        let count = (keep - self.first_input_frame) as usize;
+        self.local_inputs.drain(..count);
+        self.remote_inputs.drain(..count.min(self.remote_inputs.len()));
+        self.used_remote_inputs.drain(..count);
+        self.first_input_frame = keep;
+    }
+
+    fn check_hash(&mut self, frame: u32) {
+        if let (Some(local), Some(remote)) = (self.local_hashes.get(&frame), self.remote_hashes.get(&frame)) {
+            if local != remote && self.desync_tick.is_none() {
+                self.desync_tick = Some(frame);
+            }
+        }
+    }
+
+    fn send_inputs(&mut self) -> io::Result<()> {
+        let (first_tick, actions) = unacknowledged_inputs(&self.local_inputs, self.first_input_frame, self.remote_confirmed);
+        let packet = Packet::Input {
+            first_tick: first_tick,
+            actions: actions.to_vec(),
+            hash_tick: self.hashed,
+            hash: self.local_hashes[&self.hashed]
+        };
+        self.transport.send(&packet)
+    }
+}
+
+impl<T: Transport> Session for RollbackSession<T> {
+    fn advance(&mut self, game: &mut Game, local: Actions) -> io::Result<bool> {
+        let target = (self.frame + self.input_delay - self.first_input_frame) as usize;
+        if self.local_inputs.len() <= target {
+            self.local_inputs.push(local);
+        }
+        if let Some(frame) = self.receive()? {
+            self.rollback(game, frame);
+        }
+        self.confirm_states(game);
+        let advanced = if self.frame < self.confirmed + MAX_PREDICTION {
+            let frame = self.frame;
+            self.simulate_frame(game, frame);
+            self.frame += 1;
+            self.confirm_states(game);
+            true
+        }
+        else {
+            self.stats.stalls += 1;
+            false
+        };
+        self.send_inputs()?;
+        Ok(advanced)
+    }
+
+    fn desync_tick(&self) -> Option<u32> {
+        self.desync_tick
+    }
+
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        self.events.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use game::ARENA_SIZE;
+
+    /// Delivers every packet at the next `receive` of the other side
+    struct Pipe {
+        queues: Rc<RefCell<[Vec<Vec<u8>>; 2]>>,
+        side: usize
+    }
+
+    impl Transport for Pipe {
+        fn send(&mut self, packet: &Packet) -> io::Result<()> {
+            self.queues.borrow_mut()[1 - self.side].push(packet.encode());
+            Ok(())
+        }
+
+        fn receive(&mut self) -> io::Result<Vec<Packet>> {
+            let data: Vec<Vec<u8>> = self.queues.borrow_mut()[self.side].drain(..).collect();
+            Ok(data.iter().filter_map(|data| Packet::decode(data)).collect())
+        }
+    }
+
+    #[test]
+    fn long_matches_keep_a_bounded_history() {
+        let queues = Rc::new(RefCell::new([vec![], vec![]]));
+        let mut games = [Game::with_seed(ARENA_SIZE, 0), Game::with_seed(ARENA_SIZE, 0)];
+        let mut sessions: Vec<_> = (0..2).map(|player| {
+            RollbackSession::new(Pipe { queues: queues.clone(), side: player }, player, 3, &games[player])
+        }).collect();
+        for frame in 0..4 * HASH_HISTORY {
+            for (player, session) in sessions.iter_mut().enumerate() {
+                let input = Actions::from_bits((frame / 7 + player as u32) as u8 % 16);
+                session.advance(&mut games[player], input).unwrap();
+            }
+        }
+        for session in sessions.iter() {
+            assert!(session.frame > 3 * HASH_HISTORY);
+            assert!(session.local_inputs.len() < 20, "{} local inputs", session.local_inputs.len());
+            assert!(session.remote_inputs.len() < 20, "{} remote inputs", session.remote_inputs.len());
+            assert!(session.used_remote_inputs.len() < 20, "{} used inputs", session.used_remote_inputs.len());
+            assert!(session.snapshots.len() < 20, "{} snapshots", session.snapshots.len());
+            assert!(session.local_hashes.len() <= 2 * HASH_HISTORY as usize + 1, "{} hashes", session.local_hashes.len());
+            assert!(session.remote_hashes.len() <= 2 * HASH_HISTORY as usize + 1, "{} remote hashes", session.remote_hashes.len());
+            assert_eq!(session.desync_tick, None);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Streaming of game snapshots to spectators over TCP. Every frame is the length of a save
+//! (see `game::save`) as little endian `u32`, followed by the save itself.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+
+use game::Game;
+
+/// Number of snapshots buffered per spectator before frames are dropped
+const SPECTATOR_BACKLOG: usize = 4;
+/// Largest snapshot a spectator accepts, far more than any match needs. Longer frames mean the
+/// stream is broken or not from the game, and end the connection.
+const MAX_SNAPSHOT_SIZE: usize = 1 << 20;
+
+/// Accepts spectators in the background and sends them the state of the game
+pub struct SpectatorServer {
+    spectators: Arc<Mutex<Vec<SyncSender<Arc<String>>>>>
+}
+
+impl SpectatorServer {
+    pub fn bind(port: u16) -> io::Result<SpectatorServer> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let spectators = Arc::new(Mutex::new(vec![]));
+        let accepted = spectators.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (sender, receiver) = mpsc::sync_channel(SPECTATOR_BACKLOG);
+                accepted.lock().unwrap().push(sender);
+                thread::spawn(move || send_snapshots(stream, receiver));
+            }
+        });
+        Ok(SpectatorServer { spectators: spectators })
+    }
+
+    /// Send the current state to all spectators. Spectators which can't keep up miss snapshots.
+    pub fn broadcast(&self, game: &Game) {
+        let mut spectators = self.spectators.lock().unwrap();
+        if spectators.is_empty() {
+            return;
+        }
+        let snapshot = Arc::new(game.save());
+        spectators.retain(|spectator| !matches!(spectator.try_send(snapshot.clone()), Err(TrySendError::Disconnected(_))));
+    }
+}
+
+fn send_snapshots(mut stream: TcpStream, snapshots: Receiver<Arc<String>>) {
+    let _ = stream.set_nodelay(true);
+    for snapshot in snapshots.iter() {
+        let length = (snapshot.len() as u32).to_le_bytes();
+        if stream.write_all(&length).and_then(|_| stream.write_all(snapshot.as_bytes())).is_err() {
+            // Dropping the receiver lets the server forget about this spectator
+            return;
+        }
+    }
+}
+
+/// Receives snapshots of a game running in another process
+pub struct SpectatorClient {
+    snapshots: Receiver<Result<Game, String>>
+}
+
+impl SpectatorClient {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<SpectatorClient> {
+        let stream = TcpStream::connect(address)?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || receive_snapshots(stream, sender));
+        Ok(SpectatorClient { snapshots: receiver })
+    }
+
+    /// The most recent snapshot received since the last call, if any
+    pub fn latest(&self) -> Result<Option<Game>, String> {
+        let mut latest = None;
+        loop {
+            match self.snapshots.try_recv() {
+                Ok(snapshot) => latest = Some(snapshot?),
+                Err(mpsc::TryRecvError::Empty) => return Ok(latest),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return match latest {
+                        Some(game) => Ok(Some(game)),
+                        None => Err("The match has ended".to_string())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Block until the next snapshot arrives
+    pub fn wait(&self) -> Result<Game, String> {
+        self.snapshots.recv().map_err(|_| "The match has ended".to_string())?
+    }
+}
+
+fn receive_snapshots(mut stream: TcpStream, snapshots: mpsc::Sender<Result<Game, String>>) {
+    loop {
+        let mut length = [0; 4];
+        if stream.read_exact(&mut length).is_err() {
+            return;
+        }
+        let length = u32::from_le_bytes(length) as usize;
+        if length > MAX_SNAPSHOT_SIZE {
+            let _ = snapshots.send(Err(format!("Snapshot of {} bytes is too large", length)));
+            return;
+        }
+        let mut data = vec![0; length];
+        if stream.read_exact(&mut data).is_err() {
+            return;
+        }
+        let snapshot = String::from_utf8(data)
+            .map_err(|_| "Invalid snapshot".to_string())
+            .and_then(|text| Game::load(&text));
+        if snapshots.send(snapshot).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+
+    /// A client connected to a local listener, and the stream the listener accepted
+    fn connection() -> (SpectatorClient, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = SpectatorClient::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (client, stream)
+    }
+
+    #[test]
+    fn snapshots_arrive_as_sent() {
+        let (client, stream) = connection();
+        let game = Game::with_seed(ARENA_SIZE, 5);
+        let (sender, receiver) = mpsc::sync_channel(SPECTATOR_BACKLOG);
+        sender.send(Arc::new(game.save())).unwrap();
+        drop(sender);
+        send_snapshots(stream, receiver);
+        assert_eq!(client.wait().unwrap().save(), game.save());
+        assert!(client.wait().is_err());
+    }
+
+    #[test]
+    fn oversized_snapshots_end_the_stream() {
+        let (client, mut stream) = connection();
+        stream.write_all(&(MAX_SNAPSHOT_SIZE as u32 + 1).to_le_bytes()).unwrap();
+        drop(stream);
+        match client.wait() {
+            Err(error) => assert!(error.contains("too large"), "{}", error),
+            Ok(_) => panic!("Accepted a snapshot over the size limit")
+        }
+        assert!(client.wait().is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::env;
+
+use game::bot::Difficulty;
+use render::theme::DEFAULT_THEME;
+use net::{DEFAULT_INPUT_DELAY, MAX_INPUT_DELAY};
+
+pub const USAGE: &'static str = "Usage: amazing_grame [--bot PLAYER[:easy|medium|hard]]...
+                     [--host PORT | --join ADDRESS:PORT] [--input-delay TICKS] [--rollback]
+                     [--rollback-test] [--load FILE] [--stream PORT | --spectate ADDRESS:PORT]
+                     [--resources DIRECTORY] [--pack NAME] [--theme NAME|FILE] [--record FILE]
+                     [--export REPLAY OUTPUT [--frame-size WIDTHxHEIGHT] [--frame-step TICKS]]
+                     [--no-instant-replay] [--stats FILE]";
+
+const DEFAULT_FRAME_SIZE: [u32; 2] = [640, 360];
+const DEFAULT_FRAME_STEP: usize = 2;
+
+/// Command line options of the game
+pub struct Options {
+    /// Player slots controlled by a bot instead of the keyboard
+    pub bots: Vec<(usize, Difficulty)>,
+    /// Port on which to wait for a network player
+    pub host: Option<u16>,
+    /// Address of a hosted network game to join
+    pub join: Option<String>,
+    /// Number of ticks by which local inputs are delayed in network games
+    pub input_delay: u32,
+    /// Use rollback instead of lockstep synchronization in network games
+    pub rollback: bool,
+    /// Run the rollback netcode against a simulated lossy network and exit
+    pub rollback_test: bool,
+    /// Start from a saved game instead of a new one
+    pub load: Option<String>,
+    /// Port on which spectators can connect to watch the game
+    pub stream: Option<u16>,
+    /// Address of a streamed game to watch
+    pub spectate: Option<String>,
+    /// Directory searched for the resources before the default locations
+    pub resources: Option<String>,
+    /// Asset pack whose files replace the default resources
+    pub pack: Option<String>,
+    /// Built-in color theme or theme file
+    pub theme: String,
+    /// File the match is recorded to
+    pub record: Option<String>,
+    /// Replay to export and the GIF file or directory of PNG frames it is exported to
+    pub export: Option<(String, String)>,
+    /// Size of the exported frames in pixels
+    pub frame_size: [u32; 2],
+    /// Number of ticks per exported frame
+    pub frame_step: usize,
+    /// Replay the last seconds in slow motion when a ship is swallowed, a star delivered or the match over
+    pub instant_replay: bool,
+    /// File the statistics are written to when the match is over, as CSV if it ends with `.csv` and as JSON otherwise
+    pub stats: Option<String>
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        let mut options = Options {
+            bots: vec![],
+            host: None,
+            join: None,
+            input_delay: DEFAULT_INPUT_DELAY,
+            rollback: false,
+            rollback_test: false,
+            load: None,
+            stream: None,
+            spectate: None,
+            resources: None,
+            pack: None,
+            theme: DEFAULT_THEME.to_string(),
+            record: None,
+            export: None,
+            frame_size: DEFAULT_FRAME_SIZE,
+            frame_step: DEFAULT_FRAME_STEP,
+            instant_replay: true,
+            stats: None
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "--bot" => {
+                    let value = args.next().ok_or("--bot requires a player number".to_string())?;
+                    options.bots.push(parse_bot(&value)?);
+                }
+                "--host" => {
+                    let value = args.next().ok_or("--host requires a port".to_string())?;
+                    options.host = Some(value.parse().map_err(|_| format!("Invalid port: {}", value))?);
+                }
+                "--join" => {
+                    options.join = Some(args.next().ok_or("--join requires an address".to_string())?);
+                }
+                "--input-delay" => {
+                    let value = args.next().ok_or("--input-delay requires a number of ticks".to_string())?;
+                    options.input_delay = value.parse().map_err(|_| format!("Invalid input delay: {}", value))?;
+                    if options.input_delay > MAX_INPUT_DELAY {
+                        return Err(format!("The input delay can be at most {} ticks", MAX_INPUT_DELAY));
+                    }
+                }
+                "--load" => {
+                    options.load = Some(args.next().ok_or("--load requires a file".to_string())?);
+                }
+                "--stream" => {
+                    let value = args.next().ok_or("--stream requires a port".to_string())?;
+                    options.stream = Some(value.parse().map_err(|_| format!("Invalid port: {}", value))?);
+                }
+                "--spectate" => {
+                    options.spectate = Some(args.next().ok_or("--spectate requires an address".to_string())?);
+                }
+                "--resources" => {
+                    options.resources = Some(args.next().ok_or("--resources requires a directory".to_string())?);
+                }
+                "--pack" => {
+                    options.pack = Some(args.next().ok_or("--pack requires a name".to_string())?);
+                }
+                "--theme" => {
+                    options.theme = args.next().ok_or("--theme requires a name".to_string())?;
+                }
+                "--record" => {
+                    options.record = Some(args.next().ok_or("--record requires a file".to_string())?);
+                }
+                "--export" => {
+                    let replay = args.next().ok_or("--export requires a replay".to_string())?;
+                    let output = args.next().ok_or("--export requires an output file or directory".to_string())?;
+                    options.export = Some((replay, output));
+                }
+                "--frame-size" => {
+                    let value = args.next().ok_or("--frame-size requires a size".to_string())?;
+                    options.frame_size = parse_size(&value)?;
+                }
+                "--frame-step" => {
+                    let value = args.next().ok_or("--frame-step requires a number of ticks".to_string())?;
+                    options.frame_step = value.parse().map_err(|_| format!("Invalid frame step: {}", value))?;
+                    if options.frame_step == 0 {
+                        return Err("The frame step must be at least one tick".to_string());
+                    }
+                }
+                "--rollback" => options.rollback = true,
+                "--rollback-test" => options.rollback_test = true,
+                "--no-instant-replay" => options.instant_replay = false,
+                "--stats" => {
+                    options.stats = Some(args.next().ok_or("--stats requires a file".to_string())?);
+                }
+                _ => return Err(format!("Unknown argument: {}", arg))
+            }
+        }
+        if options.host.is_some() && options.join.is_some() {
+            return Err("--host and --join are mutually exclusive".to_string());
+        }
+        if (options.host.is_some() || options.join.is_some()) && !options.bots.is_empty() {
+            return Err("Bots are not supported in network games".to_string());
+        }
+        if (options.host.is_some() || options.join.is_some()) && options.load.is_some() {
+            return Err("Saved games can not be loaded in network games".to_string());
+        }
+        if (options.host.is_some() || options.join.is_some()) && options.record.is_some() {
+            return Err("Only local games can be recorded".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_size(value: &str) -> Result<[u32; 2], String> {
+    let parts: Vec<&str> = value.splitn(2, 'x').collect();
+    match (parts.get(0).and_then(|w| w.parse().ok()), parts.get(1).and_then(|h| h.parse().ok())) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok([width, height]),
+        _ => Err(format!("Invalid size: {}", value))
+    }
+}
+
+fn parse_bot(value: &str) -> Result<(usize, Difficulty), String> {
+    let mut parts = value.splitn(2, ':');
+    let player = parts.next().unwrap().parse::<usize>().map_err(|_| format!("Invalid player number: {}", value))?;
+    let difficulty = match parts.next() {
+        Some(name) => Difficulty::from_name(name).ok_or(format!("Unknown difficulty: {}", name))?,
+        None => Difficulty::Medium
+    };
+    Ok((player, difficulty))
+}
@@ -5,7 +5,7 @@ use std::ops::Div;
 use std::ops::Neg;
 use std::ops::AddAssign;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 #[derive(Debug)]
 pub struct Point {
     pub x : f64,
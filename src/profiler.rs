@@ -0,0 +1,212 @@
+//! Timing of the phases of the game loop, for finding out where the time of a tick goes
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use game::Game;
+
+/// Number of ticks and frames the averages shown are computed over
+const AVERAGE_WINDOW: usize = 60;
+/// Ticks kept for the CSV dump, the oldest ones are dropped beyond this
+const MAX_RECORDED_TICKS: usize = 100000;
+
+/// How long each phase of the last timestep took, in the order they ran
+#[derive(Clone, Debug, Default)]
+pub struct PhaseTimings {
+    pub phases: Vec<(&'static str, Duration)>
+}
+
+impl PhaseTimings {
+    /// Record that the phase `name` ran from `start` until now
+    pub fn record(&mut self, name: &'static str, start: Instant) {
+        self.phases.push((name, start.elapsed()));
+    }
+}
+
+/// The measurements of one tick
+#[derive(Clone, Debug)]
+pub struct TickSample {
+    pub tick: u64,
+    /// Duration of every phase in milliseconds
+    pub phases: Vec<(&'static str, f64)>,
+    pub bodies: usize,
+    pub objects: usize,
+    pub springs: usize
+}
+
+pub struct Profiler {
+    ticks: u64,
+    recent: VecDeque<TickSample>,
+    recorded: VecDeque<TickSample>,
+    update_times: VecDeque<Instant>,
+    frame_times: VecDeque<Instant>
+}
+
+impl Default for Profiler {
+    fn default() -> Profiler {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            ticks: 0,
+            recent: VecDeque::new(),
+            recorded: VecDeque::new(),
+            update_times: VecDeque::new(),
+            frame_times: VecDeque::new()
+        }
+    }
+
+    /// Collect the timings of the tick the game just simulated, as returned by `Game::timestep`
+    pub fn record_tick(&mut self, game: &Game, timings: &PhaseTimings) {
+        let phases = timings.phases.iter()
+            .map(|&(name, duration)| (name, to_millis(duration)))
+            .collect();
+        let sample = TickSample {
+            tick: self.ticks,
+            phases: phases,
+            bodies: game.sim.bodies.len(),
+            objects: game.objects.len(),
+            springs: game.springs.len()
+        };
+        self.ticks += 1;
+        push_limited(&mut self.recent, sample.clone(), AVERAGE_WINDOW);
+        push_limited(&mut self.recorded, sample, MAX_RECORDED_TICKS);
+    }
+
+    /// Call once per update of the game loop, whether the game advanced or not
+    pub fn record_update(&mut self) {
+        push_limited(&mut self.update_times, Instant::now(), AVERAGE_WINDOW);
+    }
+
+    /// Call once per rendered frame
+    pub fn record_frame(&mut self) {
+        push_limited(&mut self.frame_times, Instant::now(), AVERAGE_WINDOW);
+    }
+
+    pub fn fps(&self) -> f64 {
+        rate(&self.frame_times)
+    }
+
+    pub fn ups(&self) -> f64 {
+        rate(&self.update_times)
+    }
+
+    /// The average duration of every phase over the last ticks, in milliseconds
+    pub fn average_phases(&self) -> Vec<(&'static str, f64)> {
+        let mut averages: Vec<(&'static str, f64)> = vec![];
+        for sample in self.recent.iter() {
+            for &(name, millis) in sample.phases.iter() {
+                match averages.iter_mut().find(|&&mut (n, _)| n == name) {
+                    Some(average) => average.1 += millis,
+                    None => averages.push((name, millis))
+                }
+            }
+        }
+        for average in averages.iter_mut() {
+            average.1 /= self.recent.len() as f64;
+        }
+        averages
+    }
+
+    pub fn latest(&self) -> Option<&TickSample> {
+        self.recent.back()
+    }
+
+    /// Write all recorded ticks with one column per phase, in milliseconds
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        self.write_csv_to(&mut File::create(path)?)
+    }
+
+    /// Write the CSV with a column for every phase any recorded tick has. Ticks without timings,
+    /// like those simulated by a network session, have zeros there
+    pub fn write_csv_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut names: Vec<&'static str> = vec![];
+        for sample in self.recorded.iter() {
+            for &(name, _) in sample.phases.iter() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        let mut header = vec!["tick"];
+        header.extend(names.iter());
+        header.extend(&["total", "body_count", "object_count", "spring_count"]);
+        writeln!(out, "{}", header.join(","))?;
+        for sample in self.recorded.iter() {
+            let mut row = vec![sample.tick.to_string()];
+            row.extend(names.iter()
+                .map(|name| sample.phases.iter().find(|&&(n, _)| n == *name).map_or(0.0, |&(_, millis)| millis).to_string()));
+            // Summing starts from -0, which would be written as such for ticks without timings
+            let total = sample.phases.iter().fold(0.0, |total, &(_, millis)| total + millis);
+            row.extend(vec![total.to_string(), sample.bodies.to_string(), sample.objects.to_string(), sample.springs.to_string()]);
+            writeln!(out, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn push_limited<T>(queue: &mut VecDeque<T>, value: T, limit: usize) {
+    if queue.len() >= limit {
+        queue.pop_front();
+    }
+    queue.push_back(value);
+}
+
+/// Events per second, from the times of the last events
+fn rate(times: &VecDeque<Instant>) -> f64 {
+    match (times.front(), times.back()) {
+        (Some(first), Some(last)) if times.len() > 1 => {
+            let seconds = to_millis(last.duration_since(*first)) / 1000.0;
+            if seconds > 0.0 { (times.len() - 1) as f64 / seconds } else { 0.0 }
+        }
+        _ => 0.0
+    }
+}
+
+fn to_millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1000000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+
+    fn timings(phases: &[(&'static str, u64)]) -> PhaseTimings {
+        PhaseTimings { phases: phases.iter().map(|&(name, micros)| (name, Duration::from_micros(micros))).collect() }
+    }
+
+    fn csv(profiler: &Profiler) -> Vec<String> {
+        let mut out = vec![];
+        profiler.write_csv_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap().lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn every_phase_gets_a_column() {
+        let game = Game::with_seed(ARENA_SIZE, 0);
+        let mut profiler = Profiler::default();
+        // Network sessions record ticks without timings
+        profiler.record_tick(&game, &PhaseTimings::default());
+        profiler.record_tick(&game, &timings(&[("gravity", 500), ("collisions", 250)]));
+        profiler.record_tick(&game, &timings(&[("gravity", 1000), ("springs", 1500)]));
+        let counts = format!("{},{},{}", game.sim.bodies.len(), game.objects.len(), game.springs.len());
+        assert_eq!(csv(&profiler), vec![
+            "tick,gravity,collisions,springs,total,body_count,object_count,spring_count".to_string(),
+            format!("0,0,0,0,0,{}", counts),
+            format!("1,0.5,0.25,0,0.75,{}", counts),
+            format!("2,1,0,1.5,2.5,{}", counts)
+        ]);
+    }
+
+    #[test]
+    fn profiles_without_ticks_only_have_a_header() {
+        assert_eq!(csv(&Profiler::new()), vec!["tick,total,body_count,object_count,spring_count".to_string()]);
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use opengl_graphics::{GlGraphics, Texture};
+use opengl_graphics::glyph_cache::GlyphCache;
+use piston_window::{Context, Graphics, Viewport};
+use piston_window::character::CharacterCache;
+
+/// Something the game can be drawn on, like the window or an image, with its own textures and fonts
+pub trait Backend: Graphics {
+    type Font: CharacterCache<Texture = Self::Texture>;
+
+    /// Call `f` with a context drawing into `viewport`, clipped to it
+    fn draw<F>(&mut self, viewport: Viewport, f: F) where F: FnOnce(Context, &mut Self);
+
+    fn load_texture(path: &Path) -> Result<Self::Texture, String>;
+
+    fn load_font(path: &Path) -> Result<Self::Font, String>;
+}
+
+impl Backend for GlGraphics {
+    type Font = GlyphCache<'static>;
+
+    fn draw<F>(&mut self, viewport: Viewport, f: F) where F: FnOnce(Context, &mut Self) {
+        GlGraphics::draw(self, viewport, f)
+    }
+
+    fn load_texture(path: &Path) -> Result<Texture, String> {
+        Texture::from_path(path)
+    }
+
+    fn load_font(path: &Path) -> Result<GlyphCache<'static>, String> {
+        GlyphCache::new(path).map_err(|e| format!("{:?}", e))
+    }
+}
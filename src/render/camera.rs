@@ -0,0 +1,149 @@
+use piston_window::{Context, Transformed};
+use rand;
+
+use ::point::Point;
+use game::Game;
+use super::SCREEN_SIZE;
+
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 10.0;
+
+/// Fraction of the remaining distance to its target the camera moves per update
+const FOLLOW_SMOOTHING: f64 = 0.1;
+/// Space around the ships when following all of them, in screen pixels
+const FOLLOW_ALL_MARGIN: f64 = 200.0;
+
+/// Largest offset caused by screen shake, in screen pixels
+const MAX_SHAKE_OFFSET: f64 = 30.0;
+const SHAKE_DECAY: f64 = 0.03;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Stay where the camera was moved to
+    Free,
+    /// Keep the ship of the player in the center
+    Follow(usize),
+    /// Keep all ships on the screen
+    FollowAll,
+    /// Keep a position in the arena in the center
+    Watch(Point)
+}
+
+/// Maps positions in the arena to positions on the screen
+pub struct Camera {
+    /// The position in the arena shown in the center of the screen
+    pub center: Point,
+    pub zoom: f64,
+    pub mode: CameraMode,
+    /// Size of the area the camera draws into, in logical screen pixels (see `SCREEN_SIZE`)
+    pub viewport_size: Point,
+    /// Additional zoom chosen by the player on top of the one of the camera mode
+    pub zoom_factor: f64,
+    /// Strength of the screen shake between 0 and 1
+    shake: f64,
+    shake_offset: Point
+}
+
+impl Camera {
+    /// A camera showing the center of the arena at its natural size
+    pub fn new(arena_size: Point) -> Camera {
+        Camera {
+            center: arena_size * 0.5,
+            zoom: 1.0,
+            mode: CameraMode::Free,
+            viewport_size: SCREEN_SIZE,
+            zoom_factor: 1.0,
+            shake: 0.0,
+            shake_offset: Point{x: 0.0, y: 0.0}
+        }
+    }
+
+    pub fn update(&mut self, game: &Game) {
+        match self.mode {
+            CameraMode::Free => {}
+            CameraMode::Follow(ship_num) => {
+                if ship_num < game.score.len() {
+                    let target = game.sim.get_body(game.get_ship(ship_num).body).pos;
+                    self.move_towards(target, self.zoom_factor, game.arena_size);
+                }
+            }
+            CameraMode::FollowAll => {
+                let ships: Vec<Point> = (0..game.score.len())
+                    .map(|ship_num| game.sim.get_body(game.get_ship(ship_num).body).pos)
+                    .collect();
+                let min = ships.iter().fold(ships[0], |min, p| Point{x: min.x.min(p.x), y: min.y.min(p.y)});
+                let max = ships.iter().fold(ships[0], |max, p| Point{x: max.x.max(p.x), y: max.y.max(p.y)});
+                let extent = max - min;
+                // Zoom out until all ships fit, but never further than needed to show the whole arena
+                // and never in beyond the natural size
+                let whole_arena = (self.viewport_size.x / game.arena_size.x).min(self.viewport_size.y / game.arena_size.y);
+                let fit = ((self.viewport_size.x - 2.0 * FOLLOW_ALL_MARGIN) / extent.x)
+                    .min((self.viewport_size.y - 2.0 * FOLLOW_ALL_MARGIN) / extent.y)
+                    .max(whole_arena)
+                    .min(1.0)
+                    .max(MIN_ZOOM);
+                self.move_towards((min + max) * 0.5, fit * self.zoom_factor, game.arena_size);
+            }
+            CameraMode::Watch(target) => self.move_towards(target, self.zoom_factor, game.arena_size),
+        }
+        self.shake = (self.shake - SHAKE_DECAY).max(0.0);
+        let strength = self.shake * self.shake * MAX_SHAKE_OFFSET;
+        self.shake_offset = Point{x: rand::random::<f64>() - 0.5, y: rand::random::<f64>() - 0.5} * (2.0 * strength);
+    }
+
+    fn move_towards(&mut self, target: Point, zoom: f64, arena_size: Point) {
+        let zoom = zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+        self.zoom += (zoom - self.zoom) * FOLLOW_SMOOTHING;
+        let target = self.clamp_to_arena(target, arena_size);
+        self.center += (target - self.center) * FOLLOW_SMOOTHING;
+    }
+
+    /// Keep the view inside the arena where possible, centering it on axes where the arena is smaller
+    fn clamp_to_arena(&self, center: Point, arena_size: Point) -> Point {
+        let half_view = self.viewport_size / (2.0 * self.zoom);
+        let clamp = |value: f64, half_view: f64, size: f64| {
+            if 2.0 * half_view >= size {
+                size * 0.5
+            }
+            else {
+                value.max(half_view).min(size - half_view)
+            }
+        };
+        Point{x: clamp(center.x, half_view.x, arena_size.x), y: clamp(center.y, half_view.y, arena_size.y)}
+    }
+
+    /// Move the camera by `offset` screen pixels, which stops following any ship
+    pub fn pan(&mut self, offset: Point) {
+        self.center += offset / self.zoom;
+        self.mode = CameraMode::Free;
+    }
+
+    pub fn zoom_by(&mut self, factor: f64) {
+        self.zoom_factor = (self.zoom_factor * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        if self.mode == CameraMode::Free {
+            self.zoom = (self.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        }
+    }
+
+    /// Shake the screen, `strength` between 0 and 1
+    pub fn shake(&mut self, strength: f64) {
+        self.shake = (self.shake + strength).min(1.0);
+    }
+
+    pub fn world_to_screen(&self, pos: Point) -> Point {
+        (pos - self.center) * self.zoom + self.viewport_size * 0.5 + self.shake_offset
+    }
+
+    pub fn screen_to_world(&self, pos: Point) -> Point {
+        (pos - self.viewport_size * 0.5 - self.shake_offset) / self.zoom + self.center
+    }
+
+    /// The context for drawing in arena coordinates
+    pub fn transform(&self, context: Context) -> Context {
+        let screen_center = self.viewport_size * 0.5 + self.shake_offset;
+        context
+            .trans(screen_center.x, screen_center.y)
+            .zoom(self.zoom)
+            .trans(-self.center.x, -self.center.y)
+    }
+}
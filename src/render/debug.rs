@@ -0,0 +1,66 @@
+use piston_window::{self, Context, Transformed};
+
+use ::point::Point;
+use game::Game;
+use resources::Resources;
+use super::backend::Backend;
+use super::draw::{arrow, circle, ring};
+
+/// Length of the drawn vectors per unit of velocity, acceleration and force
+const VELOCITY_SCALE: f64 = 0.2;
+const ACCELERATION_SCALE: f64 = 0.05;
+const FORCE_SCALE: f64 = 0.1;
+
+const OUTLINE_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 0.8];
+const VELOCITY_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+const ACCELERATION_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+const CONTACT_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const WALL_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 0.8];
+const SPRING_FORCE_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 1.0];
+const ID_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+/// Length of the walls drawn, they are infinite in the simulation
+const WALL_LENGTH: f64 = 10000.0;
+
+/// Draw the physical state of all bodies, walls and springs
+pub fn render_physics_debug<G: Backend>(context: Context, gl: &mut G, resources: &mut Resources<G>, game: &Game) {
+    for wall in game.sim.walls.iter() {
+        let tangent = Point{x: -wall.normal.y, y: wall.normal.x} * WALL_LENGTH;
+        let (start, end) = (wall.pos - tangent, wall.pos + tangent);
+        piston_window::line(WALL_COLOR, 1.0, [start.x, start.y, end.x, end.y], context.transform, gl);
+        arrow(wall.pos, wall.pos + wall.normal * 50.0, 10.0, WALL_COLOR, context, gl);
+    }
+    for body in game.sim.bodies.iter() {
+        ring(body.pos, body.radius, 1.0, OUTLINE_COLOR, context, gl);
+        // Show the rotation of the body
+        let heading = body.pos + Point::from_angle(body.apos) * body.radius;
+        piston_window::line(OUTLINE_COLOR, 0.5, [body.pos.x, body.pos.y, heading.x, heading.y], context.transform, gl);
+        arrow(body.pos, body.pos + body.vel * VELOCITY_SCALE, 8.0, VELOCITY_COLOR, context, gl);
+        arrow(body.pos, body.pos + body.last_acc * ACCELERATION_SCALE, 8.0, ACCELERATION_COLOR, context, gl);
+        // Every contact is listed by both bodies
+        for &other in body.did_collide.iter().filter(|&&other| other > body.id) {
+            if let Some(other) = game.sim.bodies.iter().find(|b| b.id == other) {
+                let contact = body.pos + (other.pos - body.pos).normalized() * body.radius;
+                piston_window::line(CONTACT_COLOR, 0.5, [body.pos.x, body.pos.y, other.pos.x, other.pos.y], context.transform, gl);
+                circle(contact, 4.0, CONTACT_COLOR, context, gl);
+            }
+        }
+        piston_window::text(ID_COLOR,
+            12,
+            &body.id.to_string(),
+            &mut resources.font,
+            context.trans(body.pos.x + body.radius, body.pos.y - body.radius).transform,
+            gl);
+    }
+    for spring in game.springs.iter() {
+        let (body1, body2) = (game.sim.get_body(spring.body1), game.sim.get_body(spring.body2));
+        arrow(body1.pos, body1.pos + spring.force * FORCE_SCALE, 8.0, SPRING_FORCE_COLOR, context, gl);
+        arrow(body2.pos, body2.pos - spring.force * FORCE_SCALE, 8.0, SPRING_FORCE_COLOR, context, gl);
+        let middle = (body1.pos + body2.pos) * 0.5;
+        piston_window::text(SPRING_FORCE_COLOR,
+            12,
+            &format!("{:.0}", spring.force.norm()),
+            &mut resources.font,
+            context.trans(middle.x, middle.y).transform,
+            gl);
+    }
+}
@@ -1,12 +1,12 @@
 use piston_window;
 use piston_window::ellipse::Ellipse;
-use piston_window::{Context, Transformed};
+use piston_window::circle_arc::CircleArc;
+use piston_window::{Context, Graphics, Transformed};
 use piston_window::types::Color;
-use opengl_graphics::GlGraphics;
 
 use ::point::Point;
 
-pub fn circle(pos: Point, radius: f64, color: Color, context: Context, gl: &mut GlGraphics) {
+pub fn circle<G: Graphics>(pos: Point, radius: f64, color: Color, context: Context, gl: &mut G) {
     Ellipse {
             color: color,
             border: None,
@@ -18,6 +18,30 @@ pub fn circle(pos: Point, radius: f64, color: Color, context: Context, gl: &mut
         gl);
 }
 
-pub fn line(start: Point, end: Point, color: Color, context: Context, gl: &mut GlGraphics) {
+pub fn ring<G: Graphics>(pos: Point, radius: f64, thickness: f64, color: Color, context: Context, gl: &mut G) {
+    CircleArc::new(color, thickness, 0.0, 2.0 * ::std::f64::consts::PI)
+        .resolution(32)
+        .draw(
+            [pos.x-radius, pos.y-radius, 2.0*radius, 2.0*radius],
+            &Default::default(),
+            context.transform,
+            gl);
+}
+
+pub fn line<G: Graphics>(start: Point, end: Point, color: Color, context: Context, gl: &mut G) {
     piston_window::line(color, 1., [ start.x, start.y, end.x, end.y ], context.transform, gl);
 }
+
+/// A line from `start` to `end` with a head at `end`
+pub fn arrow<G: Graphics>(start: Point, end: Point, head_size: f64, color: Color, context: Context, gl: &mut G) {
+    let length = (end - start).norm();
+    if length == 0.0 {
+        return;
+    }
+    let direction = (end - start) / length;
+    let normal = Point{x: -direction.y, y: direction.x};
+    let head_base = end - direction * head_size;
+    for &(from, to) in [(start, end), (end, head_base + normal * (head_size * 0.6)), (end, head_base - normal * (head_size * 0.6))].iter() {
+        piston_window::line(color, 0.75, [from.x, from.y, to.x, to.y], context.transform, gl);
+    }
+}
@@ -0,0 +1,113 @@
+//! Turning replays into animations without a window. The match is simulated again and every few
+//! ticks drawn with the software renderer, into an animated GIF or a directory of PNG frames.
+
+use std::fs::{self, File};
+use std::path::Path;
+
+use gif::{Encoder, ExtensionData, Frame, Repeat};
+use image::RgbaImage;
+
+use game::TICK_RATE;
+use game::replay::Replay;
+use resources::Resources;
+use super::{render_to_image, Overlays};
+use super::particles::ParticleSystem;
+use super::software::SoftwareGraphics;
+use super::split::{SplitLayout, View};
+
+/// Levels of red, green and blue in the fixed palette of GIFs
+const PALETTE_LEVELS: [u32; 3] = [6, 7, 6];
+
+pub struct ExportSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Number of ticks per frame
+    pub step: usize,
+    pub overlays: Overlays
+}
+
+/// Write the frames of `replay` to `output`, as a GIF if it ends with `.gif` and as PNG files in the
+/// directory `output` otherwise. Returns the number of frames.
+pub fn export_replay(replay: &Replay, resources: &mut Resources<SoftwareGraphics>, output: &Path, settings: &ExportSettings) -> Result<usize, String> {
+    if settings.step == 0 {
+        return Err("The frame step must be at least one tick".to_string());
+    }
+    let gif = output.extension().is_some_and(|extension| extension == "gif");
+    let mut encoder = if gif {
+        if settings.width > u16::MAX as u32 || settings.height > u16::MAX as u32 {
+            return Err("Frames are too large for a GIF".to_string());
+        }
+        let file = File::create(output).map_err(|e| format!("Could not create {}: {}", output.display(), e))?;
+        let mut encoder = Encoder::new(file, settings.width as u16, settings.height as u16, &palette())
+            .map_err(|e| e.to_string())?;
+        encoder.write_extension(ExtensionData::Repetitions(Repeat::Infinite)).map_err(|e| e.to_string())?;
+        Some(encoder)
+    }
+    else {
+        fs::create_dir_all(output).map_err(|e| format!("Could not create {}: {}", output.display(), e))?;
+        None
+    };
+    let mut game = replay.start()?;
+    let mut view = View::new(SplitLayout::Single, &game);
+    let mut particles = ParticleSystem::new();
+    let mut frames = 0;
+    for (tick, actions) in replay.ticks.iter().enumerate() {
+        game.control(actions.clone());
+        game.timestep();
+        particles.handle_events(&game.events, &resources.theme);
+        particles.update(&game);
+        view.update(&game);
+        if tick % settings.step != 0 {
+            continue;
+        }
+        let image = render_to_image(resources, &game, &view, &particles, settings.overlays, settings.width, settings.height);
+        match encoder {
+            Some(ref mut encoder) => {
+                let frame = Frame {
+                    width: settings.width as u16,
+                    height: settings.height as u16,
+                    // The delay is given in hundredths of a second
+                    delay: (settings.step as f64 * 100.0 / TICK_RATE as f64).round() as u16,
+                    buffer: palette_indices(&image).into(),
+                    ..Frame::default()
+                };
+                encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+            }
+            None => {
+                let path = output.join(format!("frame-{:05}.png", frames));
+                image.save(&path).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            }
+        }
+        frames += 1;
+    }
+    Ok(frames)
+}
+
+/// A palette evenly covering all colors, which is good enough for the flat colors of the game
+/// and much faster than computing a palette for every frame
+fn palette() -> Vec<u8> {
+    let mut palette = vec![];
+    for r in 0..PALETTE_LEVELS[0] {
+        for g in 0..PALETTE_LEVELS[1] {
+            for b in 0..PALETTE_LEVELS[2] {
+                palette.push(level_value(r, PALETTE_LEVELS[0]));
+                palette.push(level_value(g, PALETTE_LEVELS[1]));
+                palette.push(level_value(b, PALETTE_LEVELS[2]));
+            }
+        }
+    }
+    // GIF palettes have a power of two entries
+    palette.resize(256 * 3, 0);
+    palette
+}
+
+fn palette_indices(image: &RgbaImage) -> Vec<u8> {
+    image.pixels().map(|pixel| {
+        let level = |i: usize| (pixel.data[i] as u32 * (PALETTE_LEVELS[i] - 1) + 127) / 255;
+        ((level(0) * PALETTE_LEVELS[1] + level(1)) * PALETTE_LEVELS[2] + level(2)) as u8
+    }).collect()
+}
+
+fn level_value(level: u32, levels: u32) -> u8 {
+    (level * 255 / (levels - 1)) as u8
+}
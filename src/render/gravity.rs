@@ -0,0 +1,57 @@
+use piston_window::{self, Context, Graphics};
+
+use ::point::Point;
+use game::Game;
+use game::object::ObjectType;
+use super::mix_colors;
+use super::draw::arrow;
+use super::theme::Theme;
+
+/// Distance between the arrows of the field, in arena units
+const FIELD_SPACING: f64 = 80.0;
+/// Length of the arrow for the weakest and strongest field drawn
+const ARROW_MIN_LENGTH: f64 = 5.0;
+const ARROW_MAX_LENGTH: f64 = 35.0;
+/// Field strengths mapped to the arrow lengths and colors, on a logarithmic scale
+const FIELD_MIN_STRENGTH: f64 = 100.0;
+const FIELD_MAX_STRENGTH: f64 = 2000.0;
+const WEAK_FIELD_COLOR: [f32; 4] = [0.2, 0.3, 1.0, 0.4];
+const STRONG_FIELD_COLOR: [f32; 4] = [1.0, 0.3, 0.1, 0.8];
+
+/// Number of timesteps the trajectories of the ships are predicted for
+const TRAJECTORY_STEPS: usize = 150;
+/// Only every n-th predicted position is drawn, giving a dotted line
+const TRAJECTORY_DOT_INTERVAL: usize = 3;
+
+/// Draw arrows showing the pull of the black holes and where the ships would drift without thrust
+pub fn render_gravity<G: Graphics>(context: Context, gl: &mut G, game: &Game, theme: &Theme) {
+    let columns = (game.arena_size.x / FIELD_SPACING) as usize;
+    let rows = (game.arena_size.y / FIELD_SPACING) as usize;
+    for column in 0..columns {
+        for row in 0..rows {
+            let pos = Point{x: (column as f64 + 0.5) * FIELD_SPACING, y: (row as f64 + 0.5) * FIELD_SPACING};
+            render_arrow(context, gl, pos, game.sim.gravity_at(pos));
+        }
+    }
+    for object in game.objects.iter() {
+        if let ObjectType::Ship(ship_num) = object.type_ {
+            let trajectory = game.sim.predict_trajectory(object.body, TRAJECTORY_STEPS);
+            for (i, pos) in trajectory.iter().enumerate().skip(1).step_by(TRAJECTORY_DOT_INTERVAL) {
+                let mut color = theme.ship[ship_num];
+                color[3] = 1.0 - i as f32 / trajectory.len() as f32;
+                piston_window::rectangle(color, [pos.x - 1.5, pos.y - 1.5, 3.0, 3.0], context.transform, gl);
+            }
+        }
+    }
+}
+
+fn render_arrow<G: Graphics>(context: Context, gl: &mut G, pos: Point, field: Point) {
+    let strength = field.norm();
+    if strength < FIELD_MIN_STRENGTH {
+        return;
+    }
+    let amount = ((strength / FIELD_MIN_STRENGTH).ln() / (FIELD_MAX_STRENGTH / FIELD_MIN_STRENGTH).ln()).min(1.0);
+    let color = mix_colors(WEAK_FIELD_COLOR, STRONG_FIELD_COLOR, amount as f32);
+    let tip = pos + field / strength * (ARROW_MIN_LENGTH + (ARROW_MAX_LENGTH - ARROW_MIN_LENGTH) * amount);
+    arrow(pos, tip, 6.0, color, context, gl);
+}
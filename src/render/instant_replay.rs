@@ -0,0 +1,192 @@
+//! Slow motion replays of the last seconds before something notable happened: a ship being
+//! swallowed, a star being delivered or the end of the match. They are played from the states
+//! kept in a `History`, while the game itself waits.
+
+use piston_window::RenderArgs;
+
+use ::point::Point;
+use game::{Game, TICK_RATE};
+use game::event::GameEvent;
+use resources::Resources;
+use super::backend::Backend;
+use super::camera::CameraMode;
+use super::particles::ParticleSystem;
+use super::split::{SplitLayout, View};
+use super::theme::Theme;
+use super::{letterbox, window_area, HUD_BACKGROUND_COLOR, HUD_MARGIN};
+use super::ui::{self, Anchor, Panel};
+
+/// Number of ticks shown before the highlight, three seconds of the game
+pub const REPLAY_TICKS: usize = 3 * TICK_RATE as usize;
+/// Ticks the replay advances per update
+const SLOW_MOTION: f64 = 0.4;
+/// How much closer the camera gets to the place of the highlight
+const REPLAY_ZOOM: f64 = 1.5;
+const BANNER_FONT_SIZE: u32 = 24;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Highlight {
+    Swallowed { ship: usize, pos: Point },
+    Delivered { ship: Option<usize>, pos: Point },
+    MatchOver
+}
+
+impl Highlight {
+    /// The most notable thing that happened in the last tick of `game`, `previous` is the state
+    /// one tick earlier
+    pub fn find(previous: Option<&Game>, game: &Game) -> Option<Highlight> {
+        if game.game_over && !previous.is_some_and(|previous| previous.game_over) {
+            return Some(Highlight::MatchOver);
+        }
+        let mut found = None;
+        for event in game.events.iter() {
+            match *event {
+                GameEvent::ShipSwallowed { ship, pos } => return Some(Highlight::Swallowed { ship: ship, pos: pos }),
+                GameEvent::StarDelivered { ship, pos } => found = Some(Highlight::Delivered { ship: ship, pos: pos }),
+                _ => {}
+            }
+        }
+        found
+    }
+
+    pub fn title(&self) -> String {
+        match *self {
+            Highlight::Swallowed { ship, .. } => format!("Player {} was swallowed", ship + 1),
+            Highlight::Delivered { ship: Some(ship), .. } => format!("Player {} delivered a star", ship + 1),
+            Highlight::Delivered { ship: None, .. } => "A star drifted home".to_string(),
+            Highlight::MatchOver => "Match over".to_string()
+        }
+    }
+}
+
+/// A replay being played, with its own camera and particles
+pub struct InstantReplay {
+    pub highlight: Highlight,
+    pub view: View,
+    pub particles: ParticleSystem,
+    /// The states from the oldest to the one with the highlight
+    frames: Vec<Game>,
+    /// Index of the shown state, with the fraction of the way to the next one
+    position: f64
+}
+
+impl InstantReplay {
+    /// A replay of `frames`, none if there are no frames to show
+    pub fn new(highlight: Highlight, frames: Vec<Game>) -> Option<InstantReplay> {
+        let mut view = View::new(SplitLayout::Single, frames.first()?);
+        {
+            let camera = &mut view.cameras[0];
+            match highlight {
+                Highlight::Swallowed { pos, .. } | Highlight::Delivered { pos, .. } => {
+                    camera.mode = CameraMode::Watch(pos);
+                    camera.zoom_factor = REPLAY_ZOOM;
+                }
+                Highlight::MatchOver => camera.mode = CameraMode::FollowAll
+            }
+        }
+        Some(InstantReplay {
+            highlight: highlight,
+            view: view,
+            particles: ParticleSystem::new(),
+            frames: frames,
+            position: 0.0
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.frames[(self.position as usize).min(self.frames.len() - 1)]
+    }
+
+    /// Advance the replay by one update. Returns whether it is still playing.
+    pub fn update(&mut self, theme: &Theme) -> bool {
+        let shown = self.position as usize;
+        self.position += SLOW_MOTION;
+        if self.position as usize >= self.frames.len() {
+            return false;
+        }
+        // Particles move as slowly as the game
+        if self.position as usize != shown {
+            let game = &self.frames[self.position as usize];
+            self.particles.handle_events(&game.events, theme);
+            self.particles.update(game);
+        }
+        self.view.update(&self.frames[self.position as usize]);
+        true
+    }
+}
+
+/// Show what the replay is about and how to skip it
+pub fn render_replay_banner<G: Backend>(args: &RenderArgs, gl: &mut G, resources: &mut Resources<G>, replay: &InstantReplay) {
+    let lines = vec![
+        format!("Replay: {}", replay.highlight.title()),
+        "Space: skip".to_string()
+    ];
+    gl.draw(args.viewport(), |context, gl| {
+        let area = window_area(context.get_view_size());
+        let context = letterbox(context);
+        let panel = Panel {
+            lines: &lines,
+            size: BANNER_FONT_SIZE,
+            color: resources.theme.text,
+            background: HUD_BACKGROUND_COLOR,
+            padding: 10.0
+        };
+        let size = panel.measure(&mut resources.font);
+        panel.draw(ui::place(area, size, Anchor::Bottom, HUD_MARGIN), &mut resources.font, context, gl);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::ARENA_SIZE;
+
+    fn game_with_events(events: Vec<GameEvent>) -> Game {
+        let mut game = Game::with_seed(ARENA_SIZE, 0);
+        game.events = events;
+        game
+    }
+
+    #[test]
+    fn swallowed_ships_come_before_delivered_stars() {
+        let pos = Point { x: 10.0, y: 20.0 };
+        let game = game_with_events(vec![
+            GameEvent::StarDelivered { ship: Some(0), pos: pos },
+            GameEvent::ShipSwallowed { ship: 1, pos: pos },
+            GameEvent::StarDelivered { ship: None, pos: pos }
+        ]);
+        match Highlight::find(None, &game) {
+            Some(Highlight::Swallowed { ship: 1, .. }) => {}
+            other => panic!("Found {:?}", other)
+        }
+        let game = game_with_events(vec![
+            GameEvent::Shot { ship: 0, pos: pos },
+            GameEvent::StarDelivered { ship: Some(0), pos: pos }
+        ]);
+        match Highlight::find(None, &game) {
+            Some(Highlight::Delivered { ship: Some(0), .. }) => {}
+            other => panic!("Found {:?}", other)
+        }
+        assert!(Highlight::find(None, &game_with_events(vec![GameEvent::Shot { ship: 0, pos: pos }])).is_none());
+    }
+
+    #[test]
+    fn the_end_of_the_match_is_found_once() {
+        let running = game_with_events(vec![]);
+        let mut over = running.clone();
+        over.game_over = true;
+        match Highlight::find(Some(&running), &over) {
+            Some(Highlight::MatchOver) => {}
+            other => panic!("Found {:?}", other)
+        }
+        assert!(Highlight::find(Some(&over), &over).is_none());
+        assert!(Highlight::find(Some(&running), &running).is_none());
+    }
+
+    #[test]
+    fn replays_need_frames() {
+        assert!(InstantReplay::new(Highlight::MatchOver, vec![]).is_none());
+        let replay = InstantReplay::new(Highlight::MatchOver, vec![Game::with_seed(ARENA_SIZE, 0)]).unwrap();
+        assert_eq!(replay.game().sim.time, 0.0);
+    }
+}
@@ -0,0 +1,48 @@
+use piston_window::{self, Context, Graphics, Transformed};
+use piston_window::rectangle::Rectangle;
+
+use ::point::Point;
+use game::Game;
+use super::ui::{self, Anchor, Rect};
+use super::draw::circle;
+use super::split::View;
+use super::theme::Theme;
+
+const MINIMAP_WIDTH: f64 = 320.0;
+const MINIMAP_MARGIN: f64 = 10.0;
+const MINIMAP_BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const MINIMAP_BORDER_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const MINIMAP_VIEW_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+/// Smallest radius of objects on the minimap, so stars and ships don't vanish
+const MIN_OBJECT_RADIUS: f64 = 2.0;
+
+/// Draw the whole arena scaled down at the bottom of `area`, with the areas the cameras show
+pub fn render_minimap<G: Graphics>(context: Context, gl: &mut G, game: &Game, view: &View, theme: &Theme, area: Rect) {
+    let scale = MINIMAP_WIDTH / game.arena_size.x;
+    let size = game.arena_size * scale;
+    let rect = ui::place(area, size, Anchor::Bottom, MINIMAP_MARGIN);
+    let frame = context.trans(rect[0], rect[1]);
+    piston_window::rectangle(MINIMAP_BACKGROUND_COLOR, [0.0, 0.0, size.x, size.y], frame.transform, gl);
+    let context = frame.scale(scale, scale);
+    for object in game.objects.iter() {
+        let body = game.sim.get_body(object.body);
+        circle(body.pos, body.radius.max(MIN_OBJECT_RADIUS / scale), theme.object_color(object.type_), context, gl);
+    }
+    for spring in game.springs.iter() {
+        let (start, end) = (game.sim.get_body(spring.body1).pos, game.sim.get_body(spring.body2).pos);
+        let color = game.spring_owner(spring).map_or(theme.spring, |ship_num| theme.ship[ship_num]);
+        piston_window::line(color, 0.5 / scale, [start.x, start.y, end.x, end.y], context.transform, gl);
+    }
+    for camera in view.cameras.iter() {
+        // Only the part of the view inside the arena
+        let half_visible = camera.viewport_size / (2.0 * camera.zoom);
+        let min = Point{x: (camera.center.x - half_visible.x).max(0.0), y: (camera.center.y - half_visible.y).max(0.0)};
+        let max = Point{x: (camera.center.x + half_visible.x).min(game.arena_size.x), y: (camera.center.y + half_visible.y).min(game.arena_size.y)};
+        if max.x > min.x && max.y > min.y {
+            Rectangle::new_border(MINIMAP_VIEW_COLOR, 1.0 / scale)
+                .draw([min.x, min.y, max.x - min.x, max.y - min.y], &context.draw_state, context.transform, gl);
+        }
+    }
+    Rectangle::new_border(MINIMAP_BORDER_COLOR, 1.0)
+        .draw([0.0, 0.0, size.x, size.y], &frame.draw_state, frame.transform, gl);
+}
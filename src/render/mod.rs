@@ -1,13 +1,47 @@
+pub mod backend;
+pub mod camera;
+mod debug;
 mod draw;
+pub mod export;
+mod gravity;
+pub mod instant_replay;
+mod minimap;
+pub mod particles;
+mod profiler;
+mod results;
+pub mod split;
+pub mod software;
+pub mod sprites;
+pub mod theme;
+pub mod ui;
 
-use opengl_graphics::GlGraphics;
-use piston_window::{self, Context, Transformed};
+use piston_window::{self, Context, Graphics, RenderArgs, Transformed, Viewport};
+use piston_window::image::Image;
+use piston_window::rectangle::Rectangle;
+use image::RgbaImage;
 
+use self::backend::Backend;
 use self::draw::circle;
 use self::draw::line;
+use self::draw::ring;
+use self::debug::render_physics_debug;
+use self::gravity::render_gravity;
+use self::minimap::render_minimap;
+use self::particles::ParticleSystem;
+use self::software::SoftwareGraphics;
+pub use self::instant_replay::render_replay_banner;
+pub use self::profiler::render_profiler;
+pub use self::results::render_results;
+use self::split::{SplitLayout, View};
+use self::sprites::Sprites;
+use self::theme::{Marker, Theme};
+use self::ui::{Anchor, Button, Label, Panel, Rect};
+use ::point::Point;
 use simulation::body::Body;
+use game::object::ObjectType;
 use game::object::ObjectType::*;
-use game::Game;
+use game::{Game, SPAWN_PROTECTION_TIME, SPRING_REST_LENGTH};
+use game::spring::Spring;
 use resources::Resources;
 
 pub const SHIP_POLYGON: &'static [[f64; 2]] = &[
@@ -18,107 +52,310 @@ pub const STAR_POLYGON: &'static [[f64; 2]] = &[
     [1.0, 0.0], [-0.4999999999999998, 0.8660254037844387], [-0.5000000000000004, -0.8660254037844384]
 ];
 
-const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
-const BLACK_HOLE_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
-const BULLET_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
-const SPRING_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
-const STAR_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
-const SHIP_COLOR: [[f32; 4]; 2] = [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 1.0, 1.0]];
-const MOTHERSHIP_COLOR: [f32; 4] = [0.0, 1.0, 0.3, 1.0];
+/// Size of the screen everything is drawn on. The window shows it scaled to fit, with black bars
+/// on the sides if the aspect ratios differ.
+pub const SCREEN_SIZE: Point = Point{x: 1920.0, y: 1080.0};
+
+/// Springs pulling with at least this force are drawn as overstrained
+const SPRING_OVERSTRAIN_FORCE: f64 = 1500.0;
+const SPRING_FLASH_FREQUENCY: f64 = 4.0;
+const SPRING_MIN_RADIUS: f64 = 0.5;
+const SPRING_MAX_RADIUS: f64 = 2.5;
+const SPRING_COILS: usize = 8;
+const SPRING_COIL_WIDTH: f64 = 6.0;
 const SCORE_COLOR: [f32; 4] = [1.0, 0.5, 0.5, 1.0];
-const HELP_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+/// Size of the player markers on ships, relative to the radius of the ship
+const SHIP_MARKER_SIZE: f64 = 0.35;
+const SPRING_MARKER_SIZE: f64 = 7.0;
+const SPAWN_PROTECTION_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+/// Distance of the HUD from the edges of the window
+const HUD_MARGIN: f64 = 10.0;
+const HUD_FONT_SIZE: u32 = 22;
+const HUD_BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const SCORE_SPACING: f64 = 80.0;
+const HELP_HINT: &'static str = "Press F1 for help.";
+
+/// Optional parts of the HUD
+#[derive(Clone, Copy, Debug)]
+pub struct Overlays {
+    pub help: bool,
+    pub minimap: bool,
+    /// The gravitational field and predicted trajectories of the ships
+    pub gravity: bool,
+    /// Collision shapes, vectors and contacts of the simulation
+    pub physics: bool,
+    /// Timings of the game loop, drawn by `render_profiler`
+    pub profiler: bool,
+    /// The mouse cursor in window coordinates, buttons under it are highlighted
+    pub cursor: Option<Point>
+}
+
+pub fn render<G: Backend>(args: &RenderArgs, gl: &mut G, resources: &mut Resources<G>, game: &Game, view: &View, particles: &ParticleSystem, overlays: Overlays) {
+    let window = args.viewport();
+    gl.draw(window, |_, gl| piston_window::clear(resources.theme.background, gl));
+    let window_size = [args.width as f64, args.height as f64];
+    let scale = letterbox_scale(window_size);
+    let offset = [(window_size[0] - SCREEN_SIZE.x * scale) * 0.5, (window_size[1] - SCREEN_SIZE.y * scale) * 0.5];
+    let pixels_per_point = [args.draw_width as f64 / window_size[0], args.draw_height as f64 / window_size[1]];
+    for (i, rect) in view.layout.viewports().iter().enumerate() {
+        // Drawing into a smaller GL viewport clips everything outside of it
+        let x = (offset[0] + rect[0] * scale) * pixels_per_point[0];
+        let y = (offset[1] + rect[1] * scale) * pixels_per_point[1];
+        let width = rect[2] * scale * pixels_per_point[0];
+        let height = rect[3] * scale * pixels_per_point[1];
+        let viewport = Viewport {
+            rect: [x.round() as i32, (args.draw_height as f64 - y - height).round() as i32, width.round() as i32, height.round() as i32],
+            ..window
+        };
+        gl.draw(viewport, |context, gl| {
+            let context = context.scale(scale, scale);
+            render_world(view.cameras[i].transform(context), gl, resources, game, particles, overlays);
+            if let Some(player) = view.player(i) {
+                let text = score_text(game, player);
+                let label = score_label(&text, &resources.theme, player);
+                let size = label.measure(&mut resources.font);
+                let score_rect = ui::place([0.0, 0.0, rect[2], rect[3]], size, Anchor::Top, HUD_MARGIN);
+                label.draw(score_rect, &mut resources.font, context, gl);
+            }
+        });
+    }
+    gl.draw(window, |context, gl| {
+        let area = window_area(context.get_view_size());
+        let context = letterbox(context);
+        if view.layout == SplitLayout::Single {
+            render_scores(context, gl, resources, game, area);
+        }
+        else {
+            for rect in view.layout.viewports() {
+                Rectangle::new_border(resources.theme.divider, 1.0).draw(rect, &context.draw_state, context.transform, gl);
+            }
+        }
+        if overlays.minimap {
+            render_minimap(context, gl, game, view, &resources.theme, area);
+        }
+        if overlays.help {
+            print_help(context, gl, resources, area);
+        }
+        else {
+            let rect = help_button_rect(resources, area);
+            let highlighted = overlays.cursor.is_some_and(|cursor| ui::contains(rect, window_to_screen(window_size, cursor)));
+            help_button(&resources.theme, highlighted).draw(rect, &mut resources.font, context, gl);
+        }
+    });
+}
 
-pub fn render(context: Context, gl: &mut GlGraphics, resources: &mut Resources, game: &Game, draw_help: bool) {
-    piston_window::clear(BACKGROUND_COLOR, gl);
+/// Draw the game like `render` into an image of `width` times `height` pixels, without OpenGL
+pub fn render_to_image(resources: &mut Resources<SoftwareGraphics>, game: &Game, view: &View, particles: &ParticleSystem, overlays: Overlays, width: u32, height: u32) -> RgbaImage {
+    let args = RenderArgs { ext_dt: 0.0, width: width, height: height, draw_width: width, draw_height: height };
+    let mut canvas = SoftwareGraphics::new(width, height);
+    render(&args, &mut canvas, resources, game, view, particles, overlays);
+    canvas.image
+}
+
+/// Whether `pos`, in window coordinates like the mouse cursor, is on the button showing the help
+pub fn is_over_help_button<G: Backend>(resources: &mut Resources<G>, window_size: [f64; 2], pos: Point) -> bool {
+    let rect = help_button_rect(resources, window_area(window_size));
+    ui::contains(rect, window_to_screen(window_size, pos))
+}
+
+fn help_button(theme: &Theme, highlighted: bool) -> Button<'static> {
+    Button {
+        label: Label { text: HELP_HINT, size: HUD_FONT_SIZE, color: theme.text },
+        border: theme.divider,
+        highlighted: highlighted,
+        padding: 4.0
+    }
+}
+
+fn help_button_rect<G: Backend>(resources: &mut Resources<G>, area: Rect) -> Rect {
+    let size = help_button(&resources.theme, false).measure(&mut resources.font);
+    ui::place(area, size, Anchor::TopLeft, HUD_MARGIN)
+}
+
+fn render_world<G: Backend>(context: Context, gl: &mut G, resources: &mut Resources<G>, game: &Game, particles: &ParticleSystem, overlays: Overlays) {
+    if overlays.gravity {
+        render_gravity(context, gl, game, &resources.theme);
+    }
+    particles.render(context, gl);
+    let theme = resources.theme.clone();
     for object in game.objects.iter() {
-        match object.type_ {
-            Ship(num) => render_ship(context, gl, game.sim.get_body(object.body), num),
-            Star => render_body(context, gl, game.sim.get_body(object.body)),
-            BlackHole => render_black_hole(context, gl, game.sim.get_body(object.body)),
-            Mothership => render_mothership(context, gl, game.sim.get_body(object.body)),
-            Bullet(ship, _) => {
-                let ship = game.get_ship(ship);
-                render_bullet(context, gl, game.sim.get_body(object.body), game.sim.get_body(ship.body));
+        let body = game.sim.get_body(object.body);
+        if let Bullet(ship, _) = object.type_ {
+            let ship = game.sim.get_body(game.get_ship(ship).body);
+            line(body.pos, ship.pos, theme.bullet, context, gl);
+        }
+        if !render_sprite(context, gl, &resources.sprites, object.type_, body) {
+            let color = theme.object_color(object.type_);
+            match object.type_ {
+                Ship(_) => render_ship(context, gl, body, color),
+                Star => render_body(context, gl, body, color),
+                BlackHole | Mothership | Bullet(..) => circle(body.pos, body.radius, color, context, gl)
+            }
+        }
+        if let Ship(num) = object.type_ {
+            theme.marker[num].draw(body.pos, body.apos, body.radius * SHIP_MARKER_SIZE, theme.background, context, gl);
+            if game.is_spawn_protected(num) {
+                let remaining = (game.spawn_protection[num] - game.sim.time) / SPAWN_PROTECTION_TIME;
+                render_spawn_protection(context, gl, body, remaining);
             }
         }
     }
     for spring in game.springs.iter() {
-        render_spring(context, gl, game.sim.get_body(spring.body1), game.sim.get_body(spring.body2));
-    }
-    // Score
-    piston_window::text(SHIP_COLOR[0],
-            22,
-            &format!("Score: {}", game.score[0]),
-            &mut resources.font,
-            context.trans(600.0, 20.0).transform,
-            gl);
-    piston_window::text(SHIP_COLOR[1],
-            22,
-            &format!("Score: {}", game.score[1]),
-            &mut resources.font,
-            context.trans(800.0, 20.0).transform,
-            gl);
-    // Help text
-    if draw_help {
-        print_help(context, gl, resources);
+        let owner = game.spring_owner(spring);
+        let color = owner.map_or(theme.spring, |ship_num| theme.ship[ship_num]);
+        let marker = owner.map_or(Marker::None, |ship_num| theme.marker[ship_num]);
+        render_spring(context, gl, spring, game.sim.get_body(spring.body1), game.sim.get_body(spring.body2), color, marker, &theme, game.sim.time);
     }
-    else {
-        piston_window::text(HELP_COLOR,
-            22,
-            &format!("Press F1 for help."),
-            &mut resources.font,
-            context.trans(10.0, 20.0).transform,
-            gl);
+    if overlays.physics {
+        render_physics_debug(context, gl, resources, game);
     }
 }
 
-fn print_help(context: Context, gl: &mut GlGraphics, resources: &mut Resources) {
-    let help_text = "F1: hide help\nF8: restart\n\nPlayer one:\nw: forward\na: turn left\nd: turn right\nleft shift: shoot\nr: reset\n\nPlayer two:\nUp: forward\nLeft: turn left\nRight: turn right\nright shift: shoot\nBackspace: reset\n\nGoal: \nBring the stars (yellow) to the mothership (green)\nDon't crash into the black holes (grey)\n";
-    for (i, line) in help_text.split("\n").enumerate() {
-        piston_window::text(HELP_COLOR,
-            22,
-            line,
-            &mut resources.font,
-            context.trans(10.0, 20.0 + (i as f64) * 30.0).transform,
-            gl);
+fn score_text(game: &Game, player: usize) -> String {
+    format!("Score: {}", game.score[player])
+}
+
+fn score_label<'a>(text: &'a str, theme: &Theme, player: usize) -> Label<'a> {
+    Label { text: text, size: HUD_FONT_SIZE, color: theme.ship[player] }
+}
+
+/// The scores of all players next to each other at the top of the window
+fn render_scores<G: Backend>(context: Context, gl: &mut G, resources: &mut Resources<G>, game: &Game, area: Rect) {
+    let texts: Vec<String> = (0..game.score.len()).map(|player| score_text(game, player)).collect();
+    let labels: Vec<Label> = texts.iter().enumerate().map(|(player, text)| score_label(text, &resources.theme, player)).collect();
+    let sizes: Vec<Point> = labels.iter().map(|label| label.measure(&mut resources.font)).collect();
+    let rects = ui::row(area, &sizes, SCORE_SPACING, Anchor::Top, HUD_MARGIN);
+    for (label, rect) in labels.iter().zip(rects.into_iter()) {
+        label.draw(rect, &mut resources.font, context, gl);
     }
 }
 
-fn render_mothership(context: Context, gl: &mut GlGraphics, body: &Body) {
-    circle(body.pos, body.radius, MOTHERSHIP_COLOR, context, gl);
+/// Scale and position `SCREEN_SIZE` to fill as much of the window as possible without distortion
+fn letterbox(context: Context) -> Context {
+    let window_size = context.get_view_size();
+    let scale = letterbox_scale(window_size);
+    context
+        .trans((window_size[0] - SCREEN_SIZE.x * scale) * 0.5, (window_size[1] - SCREEN_SIZE.y * scale) * 0.5)
+        .scale(scale, scale)
+}
+
+fn letterbox_scale(window_size: [f64; 2]) -> f64 {
+    (window_size[0] / SCREEN_SIZE.x).min(window_size[1] / SCREEN_SIZE.y)
+}
+
+/// The whole window in the coordinates of the letterboxed screen, including the bars at the sides,
+/// so the HUD can stick to the edges of the window
+fn window_area(window_size: [f64; 2]) -> Rect {
+    let corner = window_to_screen(window_size, Point{x: 0.0, y: 0.0});
+    let scale = letterbox_scale(window_size);
+    [corner.x, corner.y, window_size[0] / scale, window_size[1] / scale]
+}
+
+fn window_to_screen(window_size: [f64; 2], pos: Point) -> Point {
+    let scale = letterbox_scale(window_size);
+    Point{
+        x: (pos.x - (window_size[0] - SCREEN_SIZE.x * scale) * 0.5) / scale,
+        y: (pos.y - (window_size[1] - SCREEN_SIZE.y * scale) * 0.5) / scale
+    }
 }
 
-fn render_black_hole(context: Context, gl: &mut GlGraphics, body: &Body) {
-    circle(body.pos, body.radius, BLACK_HOLE_COLOR, context, gl);
+fn print_help<G: Backend>(context: Context, gl: &mut G, resources: &mut Resources<G>, area: Rect) {
+    let help_text = "F1: hide help\nF2: switch camera\nF3: gravity field\nF4: physics debug\nF6: profiler\nF7: dump profile\nF10: color theme\nF11: split screen\nF12: screenshot\nM: minimap\n-/=: volume\n0: mute\nPage up/down: zoom\nHome: reset camera\nSpace: skip replay\nF5: quicksave\nF8: restart\nF9: quickload\n\nPlayer one:\nw: forward\na: turn left\nd: turn right\nleft shift: shoot\nr: reset\n\nPlayer two:\nUp: forward\nLeft: turn left\nRight: turn right\nright shift: shoot\nBackspace: reset\n\nGoal: \nBring the stars to the mothership\nDon't crash into the black holes\n";
+    let lines: Vec<String> = help_text.lines().map(|line| line.to_string()).collect();
+    let panel = Panel {
+        lines: &lines,
+        size: HUD_FONT_SIZE,
+        color: resources.theme.text,
+        background: HUD_BACKGROUND_COLOR,
+        padding: 10.0
+    };
+    let size = panel.measure(&mut resources.font);
+    panel.draw(ui::place(area, size, Anchor::TopLeft, HUD_MARGIN), &mut resources.font, context, gl);
 }
 
-fn render_bullet(context: Context, gl: &mut GlGraphics, body: &Body, ship: &Body) {
-    circle(body.pos, body.radius, BULLET_COLOR, context, gl);
-    line(body.pos, ship.pos, BULLET_COLOR, context, gl);
+/// Draw the texture of an object, if it has one, rotated with the body and covering its radius
+fn render_sprite<G: Graphics>(context: Context, gl: &mut G, sprites: &Sprites<G::Texture>, type_: ObjectType, body: &Body) -> bool {
+    match sprites.texture(type_) {
+        Some(texture) => {
+            let transform = context.transform
+                .trans(body.pos.x, body.pos.y)
+                .rot_rad(body.apos);
+            Image::new_color(sprites.tint(type_))
+                .rect([-body.radius, -body.radius, 2.0 * body.radius, 2.0 * body.radius])
+                .draw(texture, &context.draw_state, transform, gl);
+            true
+        }
+        None => false
+    }
 }
 
-fn render_body(context: Context, gl: &mut GlGraphics, body: &Body) {
+fn render_body<G: Graphics>(context: Context, gl: &mut G, body: &Body, color: [f32; 4]) {
     let transform = context.transform
         .trans(body.pos.x, body.pos.y)
         .rot_rad(body.apos)
         .scale(body.radius, body.radius);
-    piston_window::polygon(STAR_COLOR, STAR_POLYGON, transform, gl);
+    piston_window::polygon(color, STAR_POLYGON, transform, gl);
     let transform = context.transform
         .trans(body.pos.x, body.pos.y)
         .rot_rad(body.apos+3.1415/3.0)
         .scale(body.radius, body.radius);
-    piston_window::polygon(STAR_COLOR, STAR_POLYGON, transform, gl);
+    piston_window::polygon(color, STAR_POLYGON, transform, gl);
 }
 
-fn render_ship(context: Context, gl: &mut GlGraphics, ship: &Body, ship_num: usize) {
+fn render_ship<G: Graphics>(context: Context, gl: &mut G, ship: &Body, color: [f32; 4]) {
     // Set the center of the player as the origin and rotate it
     let transform = context.transform
         .trans(ship.pos.x, ship.pos.y)
         .rot_rad(ship.apos)
         .scale(ship.radius, ship.radius);
-    piston_window::polygon(SHIP_COLOR[ship_num], SHIP_POLYGON, transform, gl);
+    piston_window::polygon(color, SHIP_POLYGON, transform, gl);
+}
+
+fn render_spawn_protection<G: Graphics>(context: Context, gl: &mut G, ship: &Body, remaining: f64) {
+    // Pulse the shield and let it fade out as the protection runs out
+    let pulse = 0.5 + 0.5 * (remaining * 40.0).sin();
+    let mut color = SPAWN_PROTECTION_COLOR;
+    color[3] *= (remaining * (0.5 + 0.5 * pulse)) as f32;
+    ring(ship.pos, ship.radius * 1.3, 2.0, color, context, gl);
+}
+
+/// Draw a spring as a coil which gets thicker and redder the stronger it pulls, and flashes when overstrained.
+/// `marker` is drawn in its middle, telling which player it belongs to.
+fn render_spring<G: Graphics>(context: Context, gl: &mut G, spring: &Spring, body1: &Body, body2: &Body, color: [f32; 4], marker: Marker, theme: &Theme, time: f64) {
+    let distance = body2.pos - body1.pos;
+    let length = distance.norm();
+    if length == 0.0 {
+        return;
+    }
+    let tension = (spring.force.norm() / SPRING_OVERSTRAIN_FORCE).min(1.0);
+    let mut color = mix_colors(color, theme.spring_strained, tension as f32);
+    if spring.force.norm() >= SPRING_OVERSTRAIN_FORCE && (time * SPRING_FLASH_FREQUENCY).fract() < 0.5 {
+        color = theme.spring_strained;
+    }
+    let radius = SPRING_MIN_RADIUS + (SPRING_MAX_RADIUS - SPRING_MIN_RADIUS) * tension;
+    // The coils flatten out as the spring is stretched beyond its rest length
+    let amplitude = SPRING_COIL_WIDTH * (SPRING_REST_LENGTH / length).min(1.0);
+    let direction = distance / length;
+    let normal = Point{x: -direction.y, y: direction.x};
+    let points: Vec<Point> = (0..SPRING_COILS * 2 + 1).map(|i| {
+        let along = body1.pos + distance * (i as f64 / (SPRING_COILS * 2) as f64);
+        let side = if i == 0 || i == SPRING_COILS * 2 { 0.0 } else if i % 2 == 0 { 1.0 } else { -1.0 };
+        along + normal * (side * amplitude)
+    }).collect();
+    for segment in points.windows(2) {
+        piston_window::line(color, radius, [segment[0].x, segment[0].y, segment[1].x, segment[1].y], context.transform, gl);
+    }
+    if marker != Marker::None {
+        let middle = body1.pos + distance * 0.5;
+        circle(middle, SPRING_MARKER_SIZE, theme.background, context, gl);
+        marker.draw(middle, direction.y.atan2(direction.x), SPRING_MARKER_SIZE, color, context, gl);
+    }
 }
 
-fn render_spring(context: Context, gl: &mut GlGraphics, body1: &Body, body2: &Body) {
-    line(body1.pos, body2.pos, SPRING_COLOR, context, gl);
+fn mix_colors(from: [f32; 4], to: [f32; 4], amount: f32) -> [f32; 4] {
+    let mut color = from;
+    for i in 0..4 {
+        color[i] += (to[i] - from[i]) * amount;
+    }
+    color
 }
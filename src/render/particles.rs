@@ -0,0 +1,184 @@
+//! Purely visual particles spawned from game events. They use their own random numbers, so
+//! they never influence the game itself, but can be seeded to draw the same frames every time.
+
+use std::f64::consts::PI;
+
+use piston_window::{self, Context, Graphics};
+use rand::{self, Rng};
+
+use ::point::Point;
+use game::Game;
+use game::event::GameEvent;
+use game::rng::GameRng;
+use game::object::ObjectType;
+use super::theme::Theme;
+
+/// Particles beyond this number are not spawned
+const MAX_PARTICLES: usize = 3000;
+/// Time per update, in seconds
+const PARTICLE_DT: f64 = 1.0 / 60.0;
+/// Fraction of their velocity linear particles lose per second
+const PARTICLE_DRAG: f64 = 2.0;
+
+const EXHAUST_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+const EXHAUST_PER_TICK: usize = 2;
+const EXHAUST_SPEED: f64 = 300.0;
+/// Distance behind the center of a ship at which its exhaust appears
+const EXHAUST_OFFSET: f64 = 10.0;
+
+const SPARK_COLOR: [f32; 4] = [1.0, 1.0, 0.7, 1.0];
+/// Collisions slower than this don't produce sparks
+const SPARK_MIN_SPEED: f64 = 100.0;
+const MAX_SPARKS_PER_COLLISION: usize = 12;
+
+const DELIVERY_PARTICLES: usize = 40;
+const EXPLOSION_PARTICLES: usize = 80;
+const EXPLOSION_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+const ACCRETION_COLOR: [f32; 4] = [0.6, 0.5, 0.8, 1.0];
+const ACCRETION_PER_TICK: usize = 1;
+
+#[derive(Clone, Copy, Debug)]
+enum Motion {
+    Linear { vel: Point },
+    /// Circling around `center` while falling towards it
+    Orbit { center: Point, angular_vel: f64, fall_speed: f64 }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    pos: Point,
+    motion: Motion,
+    age: f64,
+    lifetime: f64,
+    size: f64,
+    color: [f32; 4]
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    rng: GameRng
+}
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem::with_seed(rand::random())
+    }
+
+    /// Create a particle system whose particles only depend on `seed` and the events it is given
+    pub fn with_seed(seed: u64) -> ParticleSystem {
+        ParticleSystem {
+            particles: vec![],
+            rng: GameRng::new(seed)
+        }
+    }
+
+    /// Spawn the effects of the events of the last tick, in the colors of `theme`
+    pub fn handle_events(&mut self, events: &[GameEvent], theme: &Theme) {
+        for event in events.iter() {
+            match *event {
+                GameEvent::Thrust { pos, direction, .. } => {
+                    let nozzle = pos - direction * EXHAUST_OFFSET;
+                    for _ in 0..EXHAUST_PER_TICK {
+                        let vel = -direction * EXHAUST_SPEED + self.random_direction() * self.rng.gen_range(0.0, 60.0);
+                        let lifetime = self.rng.gen_range(0.3, 0.5);
+                        self.spawn_linear(nozzle, vel, lifetime, 3.0, EXHAUST_COLOR);
+                    }
+                }
+                GameEvent::Collision { pos, speed } => {
+                    if speed > SPARK_MIN_SPEED {
+                        let count = ((speed / 50.0) as usize).min(MAX_SPARKS_PER_COLLISION);
+                        self.burst(pos, count, (100.0, 400.0), (0.2, 0.4), 2.0, SPARK_COLOR);
+                    }
+                }
+                GameEvent::StarDelivered { pos, .. } => {
+                    self.burst(pos, DELIVERY_PARTICLES, (50.0, 250.0), (0.6, 1.0), 3.0, theme.star);
+                }
+                GameEvent::ShipSwallowed { ship, pos } => {
+                    self.burst(pos, EXPLOSION_PARTICLES / 2, (100.0, 600.0), (0.5, 1.2), 4.0, theme.ship[ship]);
+                    self.burst(pos, EXPLOSION_PARTICLES / 2, (50.0, 300.0), (0.3, 0.8), 3.0, EXPLOSION_COLOR);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Move and age all particles and let matter swirl into the black holes
+    pub fn update(&mut self, game: &Game) {
+        for object in game.objects.iter().filter(|o| o.type_ == ObjectType::BlackHole) {
+            let body = game.sim.get_body(object.body);
+            for _ in 0..ACCRETION_PER_TICK {
+                let distance = body.radius * self.rng.gen_range(1.5, 3.0);
+                let lifetime = self.rng.gen_range(0.8, 1.2);
+                let particle = Particle {
+                    pos: body.pos + self.random_direction() * distance,
+                    motion: Motion::Orbit {
+                        center: body.pos,
+                        angular_vel: self.rng.gen_range(2.0, 4.0),
+                        fall_speed: (distance - body.radius) / lifetime
+                    },
+                    age: 0.0,
+                    lifetime: lifetime,
+                    size: 2.0,
+                    color: ACCRETION_COLOR
+                };
+                self.spawn(particle);
+            }
+        }
+        for particle in self.particles.iter_mut() {
+            particle.age += PARTICLE_DT;
+            match particle.motion {
+                Motion::Linear { ref mut vel } => {
+                    particle.pos += *vel * PARTICLE_DT;
+                    *vel = *vel * (1.0 - PARTICLE_DRAG * PARTICLE_DT);
+                }
+                Motion::Orbit { center, angular_vel, fall_speed } => {
+                    let offset = particle.pos - center;
+                    let distance = (offset.norm() - fall_speed * PARTICLE_DT).max(0.0);
+                    let angle = offset.y.atan2(offset.x) + angular_vel * PARTICLE_DT;
+                    particle.pos = center + Point::from_angle(angle) * distance;
+                }
+            }
+        }
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    /// Draw all particles, fading out as they age
+    pub fn render<G: Graphics>(&self, context: Context, gl: &mut G) {
+        for particle in self.particles.iter() {
+            let mut color = particle.color;
+            color[3] *= (1.0 - particle.age / particle.lifetime) as f32;
+            let half = particle.size * 0.5;
+            piston_window::rectangle(color, [particle.pos.x - half, particle.pos.y - half, particle.size, particle.size], context.transform, gl);
+        }
+    }
+
+    fn burst(&mut self, pos: Point, count: usize, speed: (f64, f64), lifetime: (f64, f64), size: f64, color: [f32; 4]) {
+        for _ in 0..count {
+            let vel = self.random_direction() * self.rng.gen_range(speed.0, speed.1);
+            let lifetime = self.rng.gen_range(lifetime.0, lifetime.1);
+            self.spawn_linear(pos, vel, lifetime, size, color);
+        }
+    }
+
+    fn spawn_linear(&mut self, pos: Point, vel: Point, lifetime: f64, size: f64, color: [f32; 4]) {
+        self.spawn(Particle {
+            pos: pos,
+            motion: Motion::Linear { vel: vel },
+            age: 0.0,
+            lifetime: lifetime,
+            size: size,
+            color: color
+        });
+    }
+
+    fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() < MAX_PARTICLES {
+            self.particles.push(particle);
+        }
+    }
+
+    fn random_direction(&mut self) -> Point {
+        Point::from_angle(self.rng.gen_range(0.0, 2.0 * PI))
+    }
+}
@@ -0,0 +1,40 @@
+use piston_window::RenderArgs;
+
+use profiler::Profiler;
+use resources::Resources;
+use super::backend::Backend;
+use super::{letterbox, window_area, HUD_BACKGROUND_COLOR, HUD_MARGIN};
+use super::ui::{self, Anchor, Panel};
+
+const PROFILER_COLOR: [f32; 4] = [0.6, 1.0, 0.6, 1.0];
+const PROFILER_FONT_SIZE: u32 = 16;
+
+/// Show the frame and update rates, the average duration of each phase of a tick and the size of the game
+pub fn render_profiler<G: Backend>(args: &RenderArgs, gl: &mut G, resources: &mut Resources<G>, profiler: &Profiler) {
+    let mut lines = vec![
+        format!("FPS: {:.1}", profiler.fps()),
+        format!("UPS: {:.1}", profiler.ups())
+    ];
+    let phases = profiler.average_phases();
+    for &(name, millis) in phases.iter() {
+        lines.push(format!("{:<16}{:7.3} ms", name, millis));
+    }
+    lines.push(format!("{:<16}{:7.3} ms", "total", phases.iter().map(|&(_, millis)| millis).sum::<f64>()));
+    if let Some(sample) = profiler.latest() {
+        lines.push(format!("bodies {} objects {} springs {}", sample.bodies, sample.objects, sample.springs));
+    }
+    lines.push("F7: write profile.csv".to_string());
+    gl.draw(args.viewport(), |context, gl| {
+        let area = window_area(context.get_view_size());
+        let context = letterbox(context);
+        let panel = Panel {
+            lines: &lines,
+            size: PROFILER_FONT_SIZE,
+            color: PROFILER_COLOR,
+            background: HUD_BACKGROUND_COLOR,
+            padding: 10.0
+        };
+        let size = panel.measure(&mut resources.font);
+        panel.draw(ui::place(area, size, Anchor::TopRight, HUD_MARGIN), &mut resources.font, context, gl);
+    });
+}
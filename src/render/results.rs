@@ -0,0 +1,68 @@
+use piston_window::{self, RenderArgs};
+
+use ::point::Point;
+use game::stats::MatchStats;
+use resources::Resources;
+use super::backend::Backend;
+use super::{letterbox, window_area, HUD_BACKGROUND_COLOR};
+use super::ui::{self, Anchor, Label};
+
+const RESULTS_FONT_SIZE: u32 = 24;
+const RESULTS_PADDING: f64 = 20.0;
+const COLUMN_SPACING: f64 = 40.0;
+
+/// Show the statistics of the finished match as a table with a column per player
+pub fn render_results<G: Backend>(args: &RenderArgs, gl: &mut G, resources: &mut Resources<G>, stats: &MatchStats) {
+    let mut rows = vec![];
+    rows.push((0..stats.players.len()).map(|i| format!("Player {}", i + 1)).collect::<Vec<_>>());
+    let mut names = vec![""];
+    let mut add_row = |name: &'static str, values: Vec<String>| {
+        names.push(name);
+        rows.push(values);
+    };
+    add_row("Score", stats.players.iter().map(|p| p.score.to_string()).collect());
+    add_row("Stars delivered", stats.players.iter().map(|p| p.stars_delivered.to_string()).collect());
+    add_row("Shots fired", stats.players.iter().map(|p| p.shots.to_string()).collect());
+    add_row("Tethers", stats.players.iter().map(|p| p.tethers.to_string()).collect());
+    add_row("Swallowed", stats.players.iter().map(|p| p.deaths.to_string()).collect());
+    add_row("Resets", stats.players.iter().map(|p| p.resets.to_string()).collect());
+    add_row("Distance", stats.players.iter().map(|p| format!("{:.0}", p.distance)).collect());
+    add_row("Boosting", stats.players.iter().map(|p| format!("{:.1} s", p.boost_time)).collect());
+    let title = format!("Match over after {:.0} s", stats.duration());
+    gl.draw(args.viewport(), |context, gl| {
+        let area = window_area(context.get_view_size());
+        let context = letterbox(context);
+        let theme = resources.theme.clone();
+        let font = &mut resources.font;
+        let mut widths = vec![names.iter().map(|name| ui::text_width(font, RESULTS_FONT_SIZE, name)).fold(0.0, f64::max)];
+        for player in 0..stats.players.len() {
+            widths.push(rows.iter().map(|row| ui::text_width(font, RESULTS_FONT_SIZE, &row[player])).fold(0.0, f64::max));
+        }
+        let line = ui::line_height(RESULTS_FONT_SIZE);
+        let title = Label { text: &title, size: RESULTS_FONT_SIZE, color: theme.text };
+        let title_size = title.measure(font);
+        let table_width = widths.iter().sum::<f64>() + COLUMN_SPACING * (widths.len() - 1) as f64;
+        let size = Point{
+            x: table_width.max(title_size.x) + 2.0 * RESULTS_PADDING,
+            y: line * (rows.len() + 2) as f64 + 2.0 * RESULTS_PADDING
+        };
+        let rect = ui::place(area, size, Anchor::Center, 0.0);
+        piston_window::rectangle(HUD_BACKGROUND_COLOR, rect, context.transform, gl);
+        title.draw(ui::place(rect, title_size, Anchor::Top, RESULTS_PADDING), font, context, gl);
+        // One empty line between the title and the table
+        let top = rect[1] + RESULTS_PADDING + 2.0 * line;
+        let left = rect[0] + (rect[2] - table_width) * 0.5;
+        for (i, (name, row)) in names.iter().zip(rows.iter()).enumerate() {
+            let y = top + line * i as f64;
+            Label { text: name, size: RESULTS_FONT_SIZE, color: theme.text }.draw([left, y, 0.0, 0.0], font, context, gl);
+            let mut x = left + widths[0] + COLUMN_SPACING;
+            for (player, value) in row.iter().enumerate() {
+                let label = Label { text: value, size: RESULTS_FONT_SIZE, color: theme.ship[player] };
+                // Numbers are aligned to the right of their column
+                let width = label.measure(font).x;
+                label.draw([x + widths[player + 1] - width, y, 0.0, 0.0], font, context, gl);
+                x += widths[player + 1] + COLUMN_SPACING;
+            }
+        }
+    });
+}
@@ -0,0 +1,231 @@
+//! Drawing into an image in memory instead of the window, for screenshots and thumbnails on machines
+//! without OpenGL. Triangles are filled without antialiasing and blended in sRGB, so images differ
+//! slightly from what the window shows.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use image::{self, Rgba, RgbaImage};
+use piston_window::{Context, DrawState, Graphics, ImageSize, Viewport};
+use piston_window::character::{Character, CharacterCache};
+use piston_window::types::{Color, FontSize};
+use rusttype;
+
+use super::backend::Backend;
+
+pub struct SoftwareGraphics {
+    pub image: RgbaImage,
+    /// The rect drawn into, in pixels from the bottom left corner like an OpenGL viewport
+    viewport: [i32; 4]
+}
+
+impl SoftwareGraphics {
+    pub fn new(width: u32, height: u32) -> SoftwareGraphics {
+        SoftwareGraphics {
+            image: RgbaImage::new(width, height),
+            viewport: [0, 0, width as i32, height as i32]
+        }
+    }
+
+    /// The pixel position of a vertex given in normalized device coordinates of the viewport
+    fn to_pixel(&self, vertex: [f32; 2]) -> [f64; 2] {
+        let rect = self.viewport;
+        let x = rect[0] as f64 + (vertex[0] as f64 + 1.0) * 0.5 * rect[2] as f64;
+        let y = rect[1] as f64 + (vertex[1] as f64 + 1.0) * 0.5 * rect[3] as f64;
+        [x, self.image.height() as f64 - y]
+    }
+
+    /// Blend `shade` into the pixels whose centers lie in the triangle. `shade` gets the barycentric
+    /// coordinates of the pixel.
+    fn fill_triangle<F>(&mut self, triangle: [[f64; 2]; 3], mut shade: F) where F: FnMut([f64; 3]) -> Color {
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+        let (width, height) = self.image.dimensions();
+        let rect = self.viewport;
+        let clip_min = [rect[0].max(0), (height as i32 - rect[1] - rect[3]).max(0)];
+        let clip_max = [(rect[0] + rect[2]).min(width as i32), (height as i32 - rect[1]).min(height as i32)];
+        let min_x = (a[0].min(b[0]).min(c[0]).floor() as i32).max(clip_min[0]);
+        let min_y = (a[1].min(b[1]).min(c[1]).floor() as i32).max(clip_min[1]);
+        let max_x = (a[0].max(b[0]).max(c[0]).ceil() as i32).min(clip_max[0]);
+        let max_y = (a[1].max(b[1]).max(c[1]).ceil() as i32).min(clip_max[1]);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = [x as f64 + 0.5, y as f64 + 0.5];
+                // Dividing by the signed area makes the weights positive inside for both windings
+                let weights = [edge(b, c, p) / area, edge(c, a, p) / area, edge(a, b, p) / area];
+                if weights.iter().all(|&w| w >= 0.0) {
+                    let color = shade(weights);
+                    blend(self.image.get_pixel_mut(x as u32, y as u32), color);
+                }
+            }
+        }
+    }
+}
+
+impl Graphics for SoftwareGraphics {
+    type Texture = SoftwareTexture;
+
+    fn clear_color(&mut self, color: Color) {
+        let pixel = Rgba([to_byte(color[0]), to_byte(color[1]), to_byte(color[2]), to_byte(color[3])]);
+        for p in self.image.pixels_mut() {
+            *p = pixel;
+        }
+    }
+
+    fn clear_stencil(&mut self, _value: u8) {}
+
+    fn tri_list<F>(&mut self, _draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]]))
+    {
+        let color = *color;
+        f(&mut |vertices: &[[f32; 2]]| {
+            for triangle in vertices.chunks(3).filter(|t| t.len() == 3) {
+                let corners = [self.to_pixel(triangle[0]), self.to_pixel(triangle[1]), self.to_pixel(triangle[2])];
+                self.fill_triangle(corners, |_| color);
+            }
+        });
+    }
+
+    fn tri_list_uv<F>(&mut self, _draw_state: &DrawState, color: &[f32; 4], texture: &SoftwareTexture, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        let color = *color;
+        f(&mut |vertices: &[[f32; 2]], uvs: &[[f32; 2]]| {
+            for (triangle, uv) in vertices.chunks(3).zip(uvs.chunks(3)).filter(|&(t, _)| t.len() == 3) {
+                let corners = [self.to_pixel(triangle[0]), self.to_pixel(triangle[1]), self.to_pixel(triangle[2])];
+                self.fill_triangle(corners, |weights| {
+                    let u = weights[0] * uv[0][0] as f64 + weights[1] * uv[1][0] as f64 + weights[2] * uv[2][0] as f64;
+                    let v = weights[0] * uv[0][1] as f64 + weights[1] * uv[1][1] as f64 + weights[2] * uv[2][1] as f64;
+                    let texel = texture.sample(u, v);
+                    [color[0] * texel[0], color[1] * texel[1], color[2] * texel[2], color[3] * texel[3]]
+                });
+            }
+        });
+    }
+}
+
+impl Backend for SoftwareGraphics {
+    type Font = SoftwareFont;
+
+    fn draw<F>(&mut self, viewport: Viewport, f: F) where F: FnOnce(Context, &mut Self) {
+        self.viewport = viewport.rect;
+        f(Context::new_viewport(viewport), self);
+    }
+
+    fn load_texture(path: &Path) -> Result<SoftwareTexture, String> {
+        image::open(path)
+            .map(|image| SoftwareTexture { image: image.to_rgba() })
+            .map_err(|e| format!("{}", e))
+    }
+
+    fn load_font(path: &Path) -> Result<SoftwareFont, String> {
+        SoftwareFont::new(path)
+    }
+}
+
+pub struct SoftwareTexture {
+    pub image: RgbaImage
+}
+
+impl SoftwareTexture {
+    /// The color at the texture coordinates `u` and `v`, from 0 to 1
+    fn sample(&self, u: f64, v: f64) -> Color {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return [0.0; 4];
+        }
+        let x = ((u * width as f64) as u32).min(width - 1);
+        let y = ((v * height as f64) as u32).min(height - 1);
+        let pixel = self.image.get_pixel(x, y).data;
+        [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0, pixel[3] as f32 / 255.0]
+    }
+}
+
+impl ImageSize for SoftwareTexture {
+    fn get_size(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+}
+
+/// Glyphs rendered with rusttype, laid out like the glyph cache of `opengl_graphics`
+pub struct SoftwareFont {
+    font: rusttype::Font<'static>,
+    glyphs: HashMap<(FontSize, char), ([f64; 2], [f64; 2], SoftwareTexture)>
+}
+
+impl SoftwareFont {
+    pub fn new(path: &Path) -> Result<SoftwareFont, String> {
+        let mut data = vec![];
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|e| format!("{}", e))?;
+        let font = rusttype::FontCollection::from_bytes(data).into_font().ok_or("Not a font".to_string())?;
+        Ok(SoftwareFont {
+            font: font,
+            glyphs: HashMap::new()
+        })
+    }
+
+    fn render_glyph(&self, size: FontSize, ch: char) -> ([f64; 2], [f64; 2], SoftwareTexture) {
+        let scale = rusttype::Scale::uniform(size as f32);
+        let mut glyph = self.font.glyph(ch).unwrap().scaled(scale);
+        if glyph.id() == rusttype::GlyphId(0) && glyph.shape().is_none() {
+            glyph = self.font.glyph('\u{FFFD}').unwrap().scaled(scale);
+        }
+        let advance = glyph.h_metrics().advance_width as f64;
+        let left = glyph.exact_bounding_box().map_or(0.0, |bounds| bounds.min.x as f64);
+        let glyph = glyph.positioned(rusttype::point(0.0, 0.0));
+        let bounds = glyph.pixel_bounding_box().unwrap_or(rusttype::Rect {
+            min: rusttype::Point { x: 0, y: 0 },
+            max: rusttype::Point { x: 0, y: 0 }
+        });
+        // One pixel of space around the glyph, as the OpenGL glyph cache does
+        let mut image = RgbaImage::new(bounds.width() as u32 + 2, bounds.height() as u32 + 2);
+        glyph.draw(|x, y, coverage| {
+            image.put_pixel(x + 1, y + 1, Rgba([255, 255, 255, to_byte(coverage)]));
+        });
+        ([left - 1.0, -bounds.min.y as f64 + 1.0], [advance, 0.0], SoftwareTexture { image: image })
+    }
+}
+
+impl CharacterCache for SoftwareFont {
+    type Texture = SoftwareTexture;
+
+    fn character<'a>(&'a mut self, size: FontSize, ch: char) -> Character<'a, SoftwareTexture> {
+        // Font sizes are in points
+        let size = ((size as f32) * 1.333).round() as u32;
+        if !self.glyphs.contains_key(&(size, ch)) {
+            let glyph = self.render_glyph(size, ch);
+            self.glyphs.insert((size, ch), glyph);
+        }
+        let &(offset, size, ref texture) = &self.glyphs[&(size, ch)];
+        Character {
+            offset: offset,
+            size: size,
+            texture: texture
+        }
+    }
+}
+
+fn edge(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: Color) {
+    let alpha = color[3].max(0.0).min(1.0);
+    for i in 0..3 {
+        let old = pixel.data[i] as f32 / 255.0;
+        pixel.data[i] = to_byte(color[i] * alpha + old * (1.0 - alpha));
+    }
+    let old_alpha = pixel.data[3] as f32 / 255.0;
+    pixel.data[3] = to_byte(alpha + old_alpha * (1.0 - alpha));
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
@@ -0,0 +1,102 @@
+use ::point::Point;
+use game::Game;
+use super::SCREEN_SIZE;
+use super::camera::{Camera, CameraMode};
+
+/// How the screen is divided between the players
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitLayout {
+    /// One view for everyone
+    Single,
+    /// The players above each other
+    Horizontal,
+    /// The players next to each other
+    Vertical,
+    /// Four views, the ones without a player show the whole match
+    Quad
+}
+
+impl SplitLayout {
+    pub fn next(self) -> SplitLayout {
+        match self {
+            SplitLayout::Single => SplitLayout::Horizontal,
+            SplitLayout::Horizontal => SplitLayout::Vertical,
+            SplitLayout::Vertical => SplitLayout::Quad,
+            SplitLayout::Quad => SplitLayout::Single
+        }
+    }
+
+    /// The areas of the screen as `[x, y, width, height]`
+    pub fn viewports(self) -> Vec<[f64; 4]> {
+        let (w, h) = (SCREEN_SIZE.x, SCREEN_SIZE.y);
+        match self {
+            SplitLayout::Single => vec![[0.0, 0.0, w, h]],
+            SplitLayout::Horizontal => vec![[0.0, 0.0, w, h * 0.5], [0.0, h * 0.5, w, h * 0.5]],
+            SplitLayout::Vertical => vec![[0.0, 0.0, w * 0.5, h], [w * 0.5, 0.0, w * 0.5, h]],
+            SplitLayout::Quad => vec![
+                [0.0, 0.0, w * 0.5, h * 0.5], [w * 0.5, 0.0, w * 0.5, h * 0.5],
+                [0.0, h * 0.5, w * 0.5, h * 0.5], [w * 0.5, h * 0.5, w * 0.5, h * 0.5]
+            ]
+        }
+    }
+}
+
+/// The cameras of all viewports of a layout
+pub struct View {
+    pub layout: SplitLayout,
+    /// One camera per viewport, in the order of `SplitLayout::viewports`
+    pub cameras: Vec<Camera>,
+    num_players: usize
+}
+
+impl View {
+    pub fn new(layout: SplitLayout, game: &Game) -> View {
+        let num_players = game.score.len();
+        let cameras = layout.viewports().iter().enumerate().map(|(i, rect)| {
+            let mut camera = Camera::new(game.arena_size);
+            camera.viewport_size = Point{x: rect[2], y: rect[3]};
+            camera.mode = match View::viewport_player(layout, num_players, i) {
+                Some(player) => CameraMode::Follow(player),
+                None => CameraMode::FollowAll
+            };
+            camera
+        }).collect();
+        View {
+            layout: layout,
+            cameras: cameras,
+            num_players: num_players
+        }
+    }
+
+    /// The player a viewport belongs to, if it isn't shared
+    pub fn player(&self, viewport: usize) -> Option<usize> {
+        View::viewport_player(self.layout, self.num_players, viewport)
+    }
+
+    fn viewport_player(layout: SplitLayout, num_players: usize, viewport: usize) -> Option<usize> {
+        if layout != SplitLayout::Single && viewport < num_players {
+            Some(viewport)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn update(&mut self, game: &Game) {
+        for camera in self.cameras.iter_mut() {
+            camera.update(game);
+        }
+    }
+
+    pub fn zoom_by(&mut self, factor: f64) {
+        for camera in self.cameras.iter_mut() {
+            camera.zoom_by(factor);
+        }
+    }
+
+    pub fn shake(&mut self, strength: f64) {
+        for camera in self.cameras.iter_mut() {
+            camera.shake(strength);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Textures for the objects of the game, configured by `sprites.txt` in the resources directory.
+//! Every line maps an object to an image in the resources and a color, for example
+//!
+//! ```text
+//! ship0 ship.png 1 0 0 1
+//! star star.png 1 1 0.5 1
+//! ```
+//!
+//! `-` instead of an image keeps the vector shape. Images are tinted with the color and ships should
+//! point to the right. Vector shapes are always drawn in the colors of the theme, also for objects
+//! without an entry or whose image can't be loaded.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use std::path::Path;
+
+use piston_window::types::Color;
+
+use assets::Assets;
+use game::object::ObjectType;
+
+pub const MANIFEST_FILE: &'static str = "sprites.txt";
+
+pub struct Sprite<T> {
+    pub texture: Option<T>,
+    pub color: Color
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub image: Option<String>,
+    pub color: Color
+}
+
+/// The sprites of all objects, with textures of type `T`
+pub struct Sprites<T> {
+    sprites: HashMap<String, Sprite<T>>
+}
+
+impl<T> Sprites<T> {
+    /// No sprites, everything is drawn with the built-in shapes and colors
+    pub fn empty() -> Sprites<T> {
+        Sprites { sprites: HashMap::new() }
+    }
+
+    /// Load the sprites listed in the manifest of the resources, creating the textures with `load_texture`.
+    /// Problems are reported and the affected objects fall back to vector shapes.
+    pub fn load<F>(assets: &Assets, load_texture: F) -> Sprites<T> where F: Fn(&Path) -> Result<T, String> {
+        let path = match assets.find(MANIFEST_FILE) {
+            Some(path) => path,
+            None => return Sprites::empty()
+        };
+        let mut text = String::new();
+        if let Err(error) = File::open(&path).and_then(|mut file| file.read_to_string(&mut text)) {
+            eprintln!("Could not read {}: {}", path.display(), error);
+            return Sprites::empty();
+        }
+        let entries = match parse_manifest(&text) {
+            Ok(entries) => entries,
+            Err(error) => {
+                eprintln!("Invalid sprite manifest {}: {}", path.display(), error);
+                return Sprites::empty();
+            }
+        };
+        let mut sprites = HashMap::new();
+        for entry in entries {
+            let texture = entry.image.and_then(|image| {
+                assets.require(&image)
+                    .and_then(|path| load_texture(&path))
+                    .map_err(|error| eprintln!("Could not load sprite {}: {}", image, error))
+                    .ok()
+            });
+            sprites.insert(entry.name, Sprite { texture: texture, color: entry.color });
+        }
+        Sprites { sprites: sprites }
+    }
+
+    pub fn get(&self, type_: ObjectType) -> Option<&Sprite<T>> {
+        self.sprites.get(&sprite_name(type_))
+    }
+
+    /// The color the image of an object is tinted with, white if the manifest doesn't set one
+    pub fn tint(&self, type_: ObjectType) -> Color {
+        self.get(type_).map_or([1.0; 4], |sprite| sprite.color)
+    }
+
+    pub fn texture(&self, type_: ObjectType) -> Option<&T> {
+        self.get(type_).and_then(|sprite| sprite.texture.as_ref())
+    }
+}
+
+/// The name of the sprite of an object in the manifest
+pub fn sprite_name(type_: ObjectType) -> String {
+    match type_ {
+        ObjectType::Ship(num) => format!("ship{}", num),
+        ObjectType::Star => "star".to_string(),
+        ObjectType::BlackHole => "blackhole".to_string(),
+        ObjectType::Mothership => "mothership".to_string(),
+        ObjectType::Bullet(..) => "bullet".to_string()
+    }
+}
+
+/// Parse a manifest, ignoring empty lines and lines starting with `#`
+pub fn parse_manifest(text: &str) -> Result<Vec<ManifestEntry>, String> {
+    let mut entries = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 6 {
+            return Err(format!("Line {}: expected name, image and four color components", number + 1));
+        }
+        let mut color = [0.0; 4];
+        for (component, field) in color.iter_mut().zip(fields[2..].iter()) {
+            *component = field.parse().map_err(|_| format!("Line {}: invalid color component {}", number + 1, field))?;
+        }
+        entries.push(ManifestEntry {
+            name: fields[0].to_string(),
+            image: if fields[1] == "-" { None } else { Some(fields[1].to_string()) },
+            color: color
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_list_images_and_colors() {
+        let text = "# Sprites\n\nship0 ship.png 1 0 0 1\n  star - 1 1 0 0.5  \n";
+        assert_eq!(parse_manifest(text).unwrap(), vec![
+            ManifestEntry { name: "ship0".to_string(), image: Some("ship.png".to_string()), color: [1.0, 0.0, 0.0, 1.0] },
+            ManifestEntry { name: "star".to_string(), image: None, color: [1.0, 1.0, 0.0, 0.5] }
+        ]);
+        assert_eq!(parse_manifest("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        assert!(parse_manifest("star - 1 1 0").is_err());
+        assert!(parse_manifest("star - 1 1 0 1 1").is_err());
+        assert!(parse_manifest("star star.png").is_err());
+        let error = parse_manifest("ship0 ship.png 1 0 0 1\n\nstar - 1 yellow 0 1\n").unwrap_err();
+        assert!(error.starts_with("Line 3:"), "{}", error);
+    }
+
+    #[test]
+    fn only_images_are_tinted_by_the_manifest() {
+        let mut sprites: Sprites<()> = Sprites::empty();
+        sprites.sprites.insert("star".to_string(), Sprite { texture: Some(()), color: [1.0, 1.0, 0.0, 1.0] });
+        sprites.sprites.insert("blackhole".to_string(), Sprite { texture: None, color: [1.0, 0.0, 0.0, 1.0] });
+        assert_eq!(sprites.texture(ObjectType::Star), Some(&()));
+        assert_eq!(sprites.tint(ObjectType::Star), [1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(sprites.texture(ObjectType::BlackHole), None);
+        assert_eq!(sprites.texture(ObjectType::Ship(0)), None);
+        assert_eq!(sprites.tint(ObjectType::Ship(0)), [1.0; 4]);
+    }
+}
@@ -0,0 +1,300 @@
+//! Colors of the game and markers distinguishing the players by shape as well as by color.
+//! Besides the built-in themes, a theme can be read from `themes/<name>.txt` in the resources, for example
+//!
+//! ```text
+//! base colorblind
+//! ship0 1 0.5 0 1
+//! ship1_marker ring
+//! ```
+//!
+//! `base` starts from a built-in theme (the default one otherwise). Colors are given as red green
+//! blue alpha and markers are `none`, `dot`, `ring`, `cross` or `bar`. Colors in the sprite manifest
+//! only tint images, shapes are drawn in the colors of the theme everywhere.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use piston_window::{self, Context, Graphics, Transformed};
+use piston_window::types::Color;
+
+use assets::Assets;
+use ::point::Point;
+use game::object::ObjectType;
+use super::draw::{circle, ring};
+
+pub const THEMES_DIRECTORY: &'static str = "themes";
+pub const DEFAULT_THEME: &'static str = "default";
+/// The built-in themes, in the order F10 cycles through them
+pub const BUILTIN_THEMES: &'static [&'static str] = &[DEFAULT_THEME, "colorblind", "tritanopia", "high_contrast"];
+
+/// A shape drawn on top of a ship and in the middle of its tethers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Marker {
+    None,
+    Dot,
+    Ring,
+    Cross,
+    Bar
+}
+
+impl Marker {
+    pub fn from_name(name: &str) -> Option<Marker> {
+        match name {
+            "none" => Some(Marker::None),
+            "dot" => Some(Marker::Dot),
+            "ring" => Some(Marker::Ring),
+            "cross" => Some(Marker::Cross),
+            "bar" => Some(Marker::Bar),
+            _ => None
+        }
+    }
+
+    /// Draw the marker centered at `pos`, `angle` being the orientation of the thing it marks
+    pub fn draw<G: Graphics>(self, pos: Point, angle: f64, size: f64, color: Color, context: Context, gl: &mut G) {
+        let transform = context.transform.trans(pos.x, pos.y).rot_rad(angle);
+        match self {
+            Marker::None => {}
+            Marker::Dot => circle(pos, size * 0.5, color, context, gl),
+            Marker::Ring => ring(pos, size * 0.6, size * 0.25, color, context, gl),
+            Marker::Cross => {
+                let arm = size * 0.6;
+                piston_window::line(color, size * 0.15, [-arm, -arm, arm, arm], transform, gl);
+                piston_window::line(color, size * 0.15, [-arm, arm, arm, -arm], transform, gl);
+            }
+            Marker::Bar => piston_window::line(color, size * 0.2, [0.0, -size * 0.7, 0.0, size * 0.7], transform, gl)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub divider: Color,
+    pub text: Color,
+    pub ship: [Color; 2],
+    pub marker: [Marker; 2],
+    pub star: Color,
+    pub black_hole: Color,
+    pub mothership: Color,
+    pub bullet: Color,
+    /// Springs without an owning ship
+    pub spring: Color,
+    pub spring_strained: Color
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            name: DEFAULT_THEME.to_string(),
+            background: [0.0, 0.0, 0.0, 1.0],
+            divider: [0.5, 0.5, 0.5, 1.0],
+            text: [1.0, 1.0, 1.0, 1.0],
+            ship: [[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 1.0, 1.0]],
+            marker: [Marker::None, Marker::None],
+            star: [1.0, 1.0, 0.0, 1.0],
+            black_hole: [0.3, 0.3, 0.3, 1.0],
+            mothership: [0.0, 1.0, 0.3, 1.0],
+            bullet: [0.0, 0.0, 1.0, 1.0],
+            spring: [0.0, 0.0, 1.0, 1.0],
+            spring_strained: [1.0, 0.2, 0.2, 1.0]
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let default = Theme::default();
+        match name {
+            DEFAULT_THEME => Some(default),
+            // Okabe-Ito colors, which stay apart for red-green color vision deficiencies
+            "colorblind" => Some(Theme {
+                name: name.to_string(),
+                ship: [[0.9, 0.6, 0.0, 1.0], [0.35, 0.7, 0.9, 1.0]],
+                marker: [Marker::Dot, Marker::Cross],
+                star: [0.95, 0.9, 0.25, 1.0],
+                black_hole: [0.45, 0.45, 0.45, 1.0],
+                mothership: [0.0, 0.6, 0.5, 1.0],
+                bullet: [0.8, 0.8, 0.8, 1.0],
+                spring: [0.8, 0.8, 0.8, 1.0],
+                spring_strained: [0.8, 0.6, 0.7, 1.0],
+                ..default
+            }),
+            // Avoids telling blue from green and yellow from white
+            "tritanopia" => Some(Theme {
+                name: name.to_string(),
+                ship: [[0.9, 0.15, 0.25, 1.0], [0.0, 0.75, 0.75, 1.0]],
+                marker: [Marker::Dot, Marker::Cross],
+                star: [1.0, 1.0, 1.0, 1.0],
+                black_hole: [0.4, 0.4, 0.4, 1.0],
+                mothership: [1.0, 0.6, 0.8, 1.0],
+                bullet: [0.7, 0.7, 0.7, 1.0],
+                spring: [0.7, 0.7, 0.7, 1.0],
+                spring_strained: [1.0, 0.5, 0.0, 1.0],
+                ..default
+            }),
+            "high_contrast" => Some(Theme {
+                name: name.to_string(),
+                divider: [1.0, 1.0, 1.0, 1.0],
+                ship: [[1.0, 0.85, 0.0, 1.0], [0.3, 0.6, 1.0, 1.0]],
+                marker: [Marker::Ring, Marker::Bar],
+                star: [1.0, 1.0, 1.0, 1.0],
+                black_hole: [0.6, 0.6, 0.6, 1.0],
+                mothership: [0.0, 1.0, 0.0, 1.0],
+                bullet: [1.0, 1.0, 1.0, 1.0],
+                spring: [1.0, 1.0, 1.0, 1.0],
+                spring_strained: [1.0, 0.0, 1.0, 1.0],
+                ..default
+            }),
+            _ => None
+        }
+    }
+
+    /// The theme file `name` in the themes directory of the resources or a file at the path `name`,
+    /// otherwise the built-in theme of that name
+    pub fn load(name: &str, assets: &Assets) -> Result<Theme, String> {
+        let path = assets.find(&format!("{}/{}.txt", THEMES_DIRECTORY, name))
+            .or_else(|| if Path::new(name).is_file() { Some(Path::new(name).to_owned()) } else { None });
+        match path {
+            Some(path) => {
+                let mut text = String::new();
+                File::open(&path)
+                    .and_then(|mut file| file.read_to_string(&mut text))
+                    .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+                let stem = path.file_stem().map_or(name.to_string(), |stem| stem.to_string_lossy().into_owned());
+                parse_theme(&stem, &text).map_err(|e| format!("Invalid theme {}: {}", path.display(), e))
+            }
+            None => Theme::builtin(name).ok_or(format!("Unknown theme {}, the built-in themes are {}", name, BUILTIN_THEMES.join(", ")))
+        }
+    }
+
+    /// The color objects of a type are drawn in, in the world as well as on the minimap
+    pub fn object_color(&self, type_: ObjectType) -> Color {
+        match type_ {
+            ObjectType::Ship(num) => self.ship[num],
+            ObjectType::Star => self.star,
+            ObjectType::BlackHole => self.black_hole,
+            ObjectType::Mothership => self.mothership,
+            ObjectType::Bullet(..) => self.bullet
+        }
+    }
+
+    /// The built-in theme after this one
+    pub fn next(&self) -> Theme {
+        let index = BUILTIN_THEMES.iter().position(|&name| name == self.name).map_or(0, |i| (i + 1) % BUILTIN_THEMES.len());
+        Theme::builtin(BUILTIN_THEMES[index]).unwrap()
+    }
+}
+
+/// Parse a theme file, ignoring empty lines and lines starting with `#`
+pub fn parse_theme(name: &str, text: &str) -> Result<Theme, String> {
+    let lines: Vec<(usize, Vec<&str>)> = text.lines()
+        .map(|line| line.trim())
+        .enumerate()
+        .filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| (number + 1, line.split_whitespace().collect()))
+        .collect();
+    let mut theme = Theme::default();
+    if let Some(&(number, ref fields)) = lines.iter().find(|&&(_, ref fields)| fields[0] == "base") {
+        let base = fields.get(1).ok_or(format!("Line {}: base requires a theme", number))?;
+        theme = Theme::builtin(base).ok_or(format!("Line {}: unknown base theme {}", number, base))?;
+    }
+    theme.name = name.to_string();
+    for &(number, ref fields) in lines.iter() {
+        let key = fields[0];
+        if key == "base" {
+            continue;
+        }
+        if key.ends_with("_marker") {
+            let ship = ship_index(&key[..key.len() - "_marker".len()]);
+            match (ship, fields.len()) {
+                (Some(ship), 2) => theme.marker[ship] = Marker::from_name(fields[1]).ok_or(format!("Line {}: unknown marker {}", number, fields[1]))?,
+                _ => return Err(format!("Line {}: expected ship0_marker or ship1_marker and a marker", number))
+            }
+            continue;
+        }
+        if fields.len() != 5 {
+            return Err(format!("Line {}: expected a name and four color components", number));
+        }
+        let mut color = [0.0; 4];
+        for (component, field) in color.iter_mut().zip(fields[1..].iter()) {
+            *component = field.parse().map_err(|_| format!("Line {}: invalid color component {}", number, field))?;
+        }
+        match key {
+            "background" => theme.background = color,
+            "divider" => theme.divider = color,
+            "text" => theme.text = color,
+            "star" => theme.star = color,
+            "blackhole" => theme.black_hole = color,
+            "mothership" => theme.mothership = color,
+            "bullet" => theme.bullet = color,
+            "spring" => theme.spring = color,
+            "spring_strained" => theme.spring_strained = color,
+            _ => match ship_index(key) {
+                Some(ship) => theme.ship[ship] = color,
+                None => return Err(format!("Line {}: unknown color {}", number, key))
+            }
+        }
+    }
+    Ok(theme)
+}
+
+fn ship_index(name: &str) -> Option<usize> {
+    match name {
+        "ship0" => Some(0),
+        "ship1" => Some(1),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_start_from_their_base() {
+        let theme = parse_theme("mine", "# Orange first player\nbase colorblind\n\nship0 1 0.5 0 1\nship1_marker ring\n").unwrap();
+        let base = Theme::builtin("colorblind").unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.ship, [[1.0, 0.5, 0.0, 1.0], base.ship[1]]);
+        assert_eq!(theme.marker, [base.marker[0], Marker::Ring]);
+        assert_eq!(theme.star, base.star);
+        assert_eq!(theme.background, base.background);
+    }
+
+    #[test]
+    fn themes_without_base_start_from_the_default() {
+        let theme = parse_theme("mine", "star 1 1 1 1").unwrap();
+        assert_eq!(theme, Theme { name: "mine".to_string(), star: [1.0, 1.0, 1.0, 1.0], ..Theme::default() });
+    }
+
+    #[test]
+    fn objects_have_the_colors_of_the_theme() {
+        let theme = Theme::builtin("high_contrast").unwrap();
+        assert_eq!(theme.object_color(ObjectType::Ship(1)), theme.ship[1]);
+        assert_eq!(theme.object_color(ObjectType::Star), theme.star);
+        assert_eq!(theme.object_color(ObjectType::BlackHole), theme.black_hole);
+        assert_eq!(theme.object_color(ObjectType::Mothership), theme.mothership);
+        assert_eq!(theme.object_color(ObjectType::Bullet(0, 0.0)), theme.bullet);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(parse_theme("mine", "planet 1 1 1 1").is_err());
+        assert!(parse_theme("mine", "ship2 1 1 1 1").is_err());
+        assert!(parse_theme("mine", "ship2_marker dot").is_err());
+        assert!(parse_theme("mine", "ship0_marker square").is_err());
+        assert!(parse_theme("mine", "base sepia").is_err());
+        assert!(parse_theme("mine", "base").is_err());
+    }
+
+    #[test]
+    fn malformed_colors_are_rejected() {
+        assert!(parse_theme("mine", "star 1 1 1").is_err());
+        assert!(parse_theme("mine", "star 1 1 1 1 1").is_err());
+        assert!(parse_theme("mine", "star 1 yellow 1 1").is_err());
+        let error = parse_theme("mine", "\nstar 1 1 1 1\nbullet 1 1 1\n").unwrap_err();
+        assert!(error.starts_with("Line 3:"), "{}", error);
+    }
+}
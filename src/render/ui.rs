@@ -0,0 +1,196 @@
+//! Layout of the HUD and menus. Everything is positioned in rects `[x, y, width, height]` in the
+//! coordinates of `SCREEN_SIZE`, by anchoring it to a side or corner of a containing rect.
+
+use piston_window::{self, Context, Graphics, Transformed};
+use piston_window::character::CharacterCache;
+use piston_window::rectangle::Rectangle;
+use piston_window::types::Color;
+
+use ::point::Point;
+
+pub type Rect = [f64; 4];
+
+/// Height of a line of text relative to the font size
+const LINE_SPACING: f64 = 1.35;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight
+}
+
+impl Anchor {
+    /// Where the anchor lies in a rect, as fractions of its width and height
+    fn fractions(self) -> (f64, f64) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0)
+        }
+    }
+}
+
+/// The rect of `size` at `anchor` in `container`, `margin` away from the sides it touches
+pub fn place(container: Rect, size: Point, anchor: Anchor, margin: f64) -> Rect {
+    let (fx, fy) = anchor.fractions();
+    // Centered axes get no margin, the others keep it on the side they are anchored to
+    let offset_x = margin * (1.0 - 2.0 * fx);
+    let offset_y = margin * (1.0 - 2.0 * fy);
+    [container[0] + (container[2] - size.x) * fx + offset_x,
+     container[1] + (container[3] - size.y) * fy + offset_y,
+     size.x,
+     size.y]
+}
+
+/// Rects for items of `sizes` next to each other, `spacing` apart, placed together like `place` does
+pub fn row(container: Rect, sizes: &[Point], spacing: f64, anchor: Anchor, margin: f64) -> Vec<Rect> {
+    let width = sizes.iter().map(|size| size.x).sum::<f64>() + spacing * sizes.len().saturating_sub(1) as f64;
+    let height = sizes.iter().map(|size| size.y).fold(0.0, f64::max);
+    let area = place(container, Point{x: width, y: height}, anchor, margin);
+    let (_, fy) = anchor.fractions();
+    let mut x = area[0];
+    sizes.iter().map(|size| {
+        let rect = [x, area[1] + (height - size.y) * fy, size.x, size.y];
+        x += size.x + spacing;
+        rect
+    }).collect()
+}
+
+pub fn contains(rect: Rect, pos: Point) -> bool {
+    pos.x >= rect[0] && pos.x < rect[0] + rect[2] && pos.y >= rect[1] && pos.y < rect[1] + rect[3]
+}
+
+pub fn text_width<C: CharacterCache>(font: &mut C, size: u32, text: &str) -> f64 {
+    font.width(size, text)
+}
+
+pub fn line_height(size: u32) -> f64 {
+    (size as f64 * LINE_SPACING).round()
+}
+
+/// A single line of text
+pub struct Label<'a> {
+    pub text: &'a str,
+    pub size: u32,
+    pub color: Color
+}
+
+impl<'a> Label<'a> {
+    pub fn measure<C: CharacterCache>(&self, font: &mut C) -> Point {
+        Point{x: text_width(font, self.size, self.text), y: line_height(self.size)}
+    }
+
+    pub fn draw<C, G>(&self, rect: Rect, font: &mut C, context: Context, gl: &mut G)
+        where C: CharacterCache, G: Graphics<Texture = C::Texture>
+    {
+        // Text is drawn above its baseline
+        piston_window::text(self.color,
+            self.size,
+            self.text,
+            font,
+            context.trans(rect[0], rect[1] + self.size as f64).transform,
+            gl);
+    }
+}
+
+/// Lines of text on a background
+pub struct Panel<'a> {
+    pub lines: &'a [String],
+    pub size: u32,
+    pub color: Color,
+    pub background: Color,
+    pub padding: f64
+}
+
+impl<'a> Panel<'a> {
+    pub fn measure<C: CharacterCache>(&self, font: &mut C) -> Point {
+        let width = self.lines.iter().map(|line| text_width(font, self.size, line)).fold(0.0, f64::max);
+        Point{x: width + 2.0 * self.padding, y: line_height(self.size) * self.lines.len() as f64 + 2.0 * self.padding}
+    }
+
+    pub fn draw<C, G>(&self, rect: Rect, font: &mut C, context: Context, gl: &mut G)
+        where C: CharacterCache, G: Graphics<Texture = C::Texture>
+    {
+        piston_window::rectangle(self.background, rect, context.transform, gl);
+        for (i, line) in self.lines.iter().enumerate() {
+            let label = Label { text: line, size: self.size, color: self.color };
+            let y = rect[1] + self.padding + line_height(self.size) * i as f64;
+            label.draw([rect[0] + self.padding, y, 0.0, 0.0], font, context, gl);
+        }
+    }
+}
+
+/// A framed label which can be clicked, see `contains`
+pub struct Button<'a> {
+    pub label: Label<'a>,
+    pub border: Color,
+    /// Whether the pointer is over the button
+    pub highlighted: bool,
+    pub padding: f64
+}
+
+impl<'a> Button<'a> {
+    pub fn measure<C: CharacterCache>(&self, font: &mut C) -> Point {
+        self.label.measure(font) + Point{x: 2.0 * self.padding, y: 2.0 * self.padding}
+    }
+
+    pub fn draw<C, G>(&self, rect: Rect, font: &mut C, context: Context, gl: &mut G)
+        where C: CharacterCache, G: Graphics<Texture = C::Texture>
+    {
+        if self.highlighted {
+            let mut fill = self.border;
+            fill[3] *= 0.3;
+            piston_window::rectangle(fill, rect, context.transform, gl);
+        }
+        Rectangle::new_border(self.border, 1.0).draw(rect, &context.draw_state, context.transform, gl);
+        self.label.draw([rect[0] + self.padding, rect[1] + self.padding, 0.0, 0.0], font, context, gl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTAINER: Rect = [100.0, 50.0, 400.0, 300.0];
+
+    #[test]
+    fn place_keeps_the_margin_on_anchored_sides() {
+        let size = Point{x: 40.0, y: 20.0};
+        assert_eq!(place(CONTAINER, size, Anchor::TopLeft, 10.0), [110.0, 60.0, 40.0, 20.0]);
+        assert_eq!(place(CONTAINER, size, Anchor::Center, 10.0), [280.0, 190.0, 40.0, 20.0]);
+        assert_eq!(place(CONTAINER, size, Anchor::BottomRight, 10.0), [450.0, 320.0, 40.0, 20.0]);
+        assert_eq!(place(CONTAINER, size, Anchor::Top, 10.0), [280.0, 60.0, 40.0, 20.0]);
+    }
+
+    #[test]
+    fn row_places_items_next_to_each_other() {
+        let sizes = [Point{x: 40.0, y: 20.0}, Point{x: 60.0, y: 30.0}];
+        let rects = row(CONTAINER, &sizes, 5.0, Anchor::Bottom, 10.0);
+        // The row is 105 wide, as high as its highest item and its items share its bottom edge
+        assert_eq!(rects, vec![[247.5, 320.0, 40.0, 20.0], [292.5, 310.0, 60.0, 30.0]]);
+        let rects = row(CONTAINER, &sizes, 5.0, Anchor::TopLeft, 0.0);
+        assert_eq!(rects, vec![[100.0, 50.0, 40.0, 20.0], [145.0, 50.0, 60.0, 30.0]]);
+        assert!(row(CONTAINER, &[], 5.0, Anchor::Center, 0.0).is_empty());
+    }
+
+    #[test]
+    fn contains_includes_only_the_top_left_edges() {
+        assert!(contains(CONTAINER, Point{x: 100.0, y: 50.0}));
+        assert!(contains(CONTAINER, Point{x: 499.0, y: 349.0}));
+        assert!(!contains(CONTAINER, Point{x: 500.0, y: 100.0}));
+        assert!(!contains(CONTAINER, Point{x: 200.0, y: 49.0}));
+    }
+}
@@ -1,18 +1,29 @@
-use std::env;
+use opengl_graphics::GlGraphics;
 
-use opengl_graphics::glyph_cache::GlyphCache;
+use assets::Assets;
+use render::backend::Backend;
+use render::sprites::Sprites;
+use render::theme::Theme;
 
-/// Additional resources needed for the game
-pub struct Resources {
-    pub font: GlyphCache<'static>
+pub const FONT_FILE: &'static str = "FiraMono-Bold.ttf";
+
+/// Additional resources needed for the game, for drawing with `G`
+pub struct Resources<G: Backend = GlGraphics> {
+    pub font: G::Font,
+    pub sprites: Sprites<G::Texture>,
+    pub theme: Theme
 }
 
-impl Resources {
-    /// Initialize and return the `Resources`
-    pub fn new() -> Resources {
-        let exe_directory = env::current_exe().unwrap().parent().unwrap().to_owned();
-        Resources {
-            font: GlyphCache::new(&exe_directory.join("../../resources/FiraMono-Bold.ttf")).unwrap()
-        }
+impl<G: Backend> Resources<G> {
+    /// Load the `Resources` from the resource directories and asset pack of `assets`, using the theme `theme`
+    pub fn new(assets: &Assets, theme: &str) -> Result<Resources<G>, String> {
+        let font_path = assets.require(FONT_FILE)?;
+        let font = G::load_font(&font_path)
+            .map_err(|e| format!("Could not load the font {}: {}", font_path.display(), e))?;
+        Ok(Resources {
+            font: font,
+            sprites: Sprites::load(assets, G::load_texture),
+            theme: Theme::load(theme, assets)?
+        })
     }
 }
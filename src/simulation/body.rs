@@ -5,6 +5,8 @@ pub struct Body {
     pub pos: Point,
     pub vel: Point,
     pub acc: Point,
+    /// The acceleration used in the last integration, since `acc` is reset afterwards
+    pub last_acc: Point,
     pub apos: f64,
     pub avel: f64,
     pub aacc: f64,
@@ -21,6 +23,7 @@ impl Body {
     pub fn integrate(&mut self, dt : f64) {
         self.vel += self.acc * dt;
         self.pos += self.vel * dt;
+        self.last_acc = self.acc;
         self.acc = Point { x: 0.0, y: 0.0 };
         self.avel += self.aacc * dt;
         self.apos += self.avel * dt;
@@ -55,6 +58,7 @@ impl Body {
             pos: pos,
             vel: Point { x: 0.0, y: 0.0 },
             acc: Point { x: 0.0, y: 0.0 },
+            last_acc: Point { x: 0.0, y: 0.0 },
             apos: 0.0,
             avel: 0.0,
             aacc: 0.0,
@@ -1,20 +1,28 @@
 pub mod body;
 
+use std::time::Instant;
+
 use ::point::Point;
 use self::body::Body;
+use ::profiler::PhaseTimings;
 use ::game::{G,DISTANCE_SCALING,WALL_RESTITUTION,FRICTION};
 
-const DT : f64 = 0.01;
+/// Simulated time per timestep, in seconds
+pub const DT : f64 = 0.01;
 // const G : f64 = 2000000.0;
 const ANGULAR_FRICTION : f64 = 0.0;
 const CLAMP_IMPULSES : bool = false;
 const BAUMGARTE_CORRECTION_STRENGTH: f64 = 10.0;
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
 
+#[derive(Clone, Debug)]
 pub struct Wall {
     pub pos: Point,
     pub normal: Point
 }
 
+#[derive(Clone)]
 pub struct Simulation {
     pub bodies: Vec<Body>,
     pub next_id: usize,
@@ -23,14 +31,29 @@ pub struct Simulation {
 }
 
 impl Simulation {
-    pub fn timestep(&mut self) {
+    /// Advance by one step and return how long each of its phases took
+    pub fn timestep(&mut self) -> PhaseTimings {
+        let mut timings = PhaseTimings::default();
+        let start = Instant::now();
         self.gravity();
+        timings.record("gravity", start);
+        let start = Instant::now();
         self.collisions();
+        timings.record("collisions", start);
+        let start = Instant::now();
         self.wall_collisions();
+        timings.record("wall_collisions", start);
+        let start = Instant::now();
         self.integrate();
+        timings.record("integrate", start);
+        let start = Instant::now();
         self.friction();
+        timings.record("friction", start);
+        let start = Instant::now();
         self.remove_bodies();
+        timings.record("remove_bodies", start);
         self.time += DT;
+        timings
     }
 
     pub fn remove_bodies(&mut self) {
@@ -97,10 +120,66 @@ impl Simulation {
         }
     }
 
+    /// Acceleration caused by the attracting bodies on a ship at `pos`
+    pub fn gravity_at(&self, pos: Point) -> Point {
+        self.gravity_without(pos, None)
+    }
+
+    /// Like `gravity_at`, but ignoring the body `skipped`
+    fn gravity_without(&self, pos: Point, skipped: Option<usize>) -> Point {
+        let mut acc = Point{x: 0.0, y: 0.0};
+        // Everything with a flag attracts ships, see `gravity`
+        for body in self.bodies.iter().filter(|b| b.gravity_flag >= 1 && Some(b.id) != skipped) {
+            let distance = pos - body.pos;
+            let length = distance.norm();
+            if length > 0.0 {
+                acc += -G * body.mass * distance / length.powi(DISTANCE_SCALING);
+            }
+        }
+        acc
+    }
+
+    /// Positions of a body over the next `steps` timesteps if only gravity and friction acted on it.
+    /// Stops early when the body falls into a black hole.
+    pub fn predict_trajectory(&self, id: usize, steps: usize) -> Vec<Point> {
+        let mut body = self.get_body(id).clone();
+        let mut positions = vec![body.pos];
+        for _ in 0..steps {
+            // The body itself stays behind where it is now
+            let acc = self.gravity_without(body.pos, Some(id));
+            body.apply_force(acc * body.mass);
+            body.integrate(DT);
+            let friction = body.vel * -FRICTION;
+            body.apply_force(friction);
+            positions.push(body.pos);
+            if self.bodies.iter().any(|b| b.gravity_flag == 1 && b.id != id && (b.pos - body.pos).norm() < b.radius + body.radius) {
+                break;
+            }
+        }
+        positions
+    }
+
+    /// Hash of the physical state of all bodies, used to detect diverging simulations
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET_BASIS, &self.time.to_bits().to_le_bytes());
+        for body in self.bodies.iter() {
+            hash = fnv1a(hash, &(body.id as u64).to_le_bytes());
+            for value in [body.pos.x, body.pos.y, body.vel.x, body.vel.y, body.apos, body.avel].iter() {
+                hash = fnv1a(hash, &value.to_bits().to_le_bytes());
+            }
+        }
+        hash
+    }
+
     pub fn get_body(&self, id: usize) -> &Body {
         self.bodies.iter().filter(|b| b.id == id).next().unwrap()
     }
 
+    /// The body with `id`, unless it was removed
+    pub fn find_body(&self, id: usize) -> Option<&Body> {
+        self.bodies.iter().find(|b| b.id == id)
+    }
+
     pub fn get_body_mut(&mut self, id: usize) -> &mut Body {
         self.bodies.iter_mut().filter(|b| b.id == id).next().unwrap()
     }
@@ -149,3 +228,57 @@ fn handle_collisions(body1 : &mut Body, body2 : &mut Body) {
         body2.did_collide.push(body1.id);
     }
 }
+
+/// 64 bit FNV-1a of `bytes`, continuing from `hash`. Unlike `DefaultHasher` it gives the same
+/// hashes in every build, so peers with different builds of the game can compare them.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ship `distance` to the right of a black hole
+    fn ship_near_black_hole(distance: f64) -> Simulation {
+        let mut black_hole = Body::new(Point{x: 500.0, y: 500.0}, 90.0, 95.0);
+        black_hole.gravity_flag = 1;
+        let mut ship = Body::new(Point{x: 500.0 + distance, y: 500.0}, 1.0, 10.0);
+        ship.gravity_flag = 2;
+        Simulation::new(vec![black_hole, ship], vec![])
+    }
+
+    #[test]
+    fn gravity_points_at_black_holes() {
+        let sim = ship_near_black_hole(200.0);
+        let acc = sim.gravity_at(Point{x: 700.0, y: 500.0});
+        assert!(acc.x < -100.0, "{:?}", acc);
+        assert!(acc.y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn trajectories_ignore_the_predicted_body() {
+        let sim = ship_near_black_hole(200.0);
+        assert_eq!(sim.predict_trajectory(1, 10).len(), 11);
+        let trajectory = sim.predict_trajectory(1, 1000);
+        assert!(trajectory.len() > 2 && trajectory.len() < 1001, "{} points", trajectory.len());
+        assert!(trajectory.last().unwrap().x < 700.0);
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn state_hashes_only_depend_on_the_state() {
+        let sim = ship_near_black_hole(200.0);
+        // Pinned, since peers with different builds compare them
+        assert_eq!(sim.state_hash(), 0x43608924f60db80e);
+        let mut moved = sim.clone();
+        moved.bodies[1].pos.x += 1e-9;
+        assert!(moved.state_hash() != sim.state_hash());
+    }
+}
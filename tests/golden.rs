@@ -0,0 +1,96 @@
+//! Renders seeded scenes with the software renderer and compares them to the reference images in
+//! `tests/golden`. Run with `UPDATE_GOLDEN=1` to replace the references after intended changes.
+
+extern crate amazing_grame;
+extern crate image;
+
+use std::env;
+use std::path::PathBuf;
+
+use amazing_grame::assets::Assets;
+use amazing_grame::game::{Game, ARENA_SIZE};
+use amazing_grame::game::input::Actions;
+use amazing_grame::render::{self, Overlays};
+use amazing_grame::render::particles::ParticleSystem;
+use amazing_grame::render::split::{SplitLayout, View};
+use amazing_grame::render::software::SoftwareGraphics;
+use amazing_grame::render::theme::{Theme, DEFAULT_THEME};
+use amazing_grame::resources::Resources;
+use image::RgbaImage;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 400;
+const SEED: u64 = 17;
+const TICKS: usize = 90;
+/// Channels may differ this much from the reference, e.g. from rounding in other math libraries
+const CHANNEL_TOLERANCE: i16 = 8;
+/// Fraction of the pixels which may differ more than `CHANNEL_TOLERANCE`
+const PIXEL_TOLERANCE: f64 = 0.002;
+
+/// Play a seeded game in which the first ship circles and the second one shoots straight ahead
+fn play(layout: SplitLayout, theme: &Theme) -> (Game, View, ParticleSystem) {
+    let mut game = Game::with_seed(ARENA_SIZE, SEED);
+    let mut view = View::new(layout, &game);
+    let mut particles = ParticleSystem::with_seed(SEED);
+    for tick in 0..TICKS {
+        let circling = Actions { boost: true, rotate_left: true, ..Actions::default() };
+        let shooting = Actions { boost: true, shoot: tick % 30 == 0, ..Actions::default() };
+        game.control(vec![circling, shooting]);
+        game.timestep();
+        particles.handle_events(&game.events, theme);
+        particles.update(&game);
+        view.update(&game);
+    }
+    (game, view, particles)
+}
+
+fn resources() -> Resources<SoftwareGraphics> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let assets = Assets::locate(Some(&directory), None).unwrap();
+    Resources::new(&assets, DEFAULT_THEME).unwrap()
+}
+
+fn check(name: &str, overlays: Overlays, layout: SplitLayout) {
+    let mut resources = resources();
+    let (game, view, particles) = play(layout, &resources.theme);
+    let image = render::render_to_image(&mut resources, &game, &view, &particles, overlays, WIDTH, HEIGHT);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.png", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save(&path).unwrap();
+        return;
+    }
+    let reference = image::open(&path).unwrap_or_else(|error| panic!("Could not open {}: {}", path.display(), error)).to_rgba();
+    assert_eq!(reference.dimensions(), image.dimensions());
+    let differing = count_differing_pixels(&reference, &image);
+    let allowed = (PIXEL_TOLERANCE * (WIDTH * HEIGHT) as f64) as usize;
+    if differing > allowed {
+        let actual = env::temp_dir().join(format!("{}-actual.png", name));
+        image.save(&actual).unwrap();
+        panic!("{} pixels differ from {}, at most {} may. The rendered image is at {}",
+               differing, path.display(), allowed, actual.display());
+    }
+}
+
+fn count_differing_pixels(reference: &RgbaImage, image: &RgbaImage) -> usize {
+    reference.pixels().zip(image.pixels())
+        .filter(|&(a, b)| a.data.iter().zip(b.data.iter()).any(|(&x, &y)| (x as i16 - y as i16).abs() > CHANNEL_TOLERANCE))
+        .count()
+}
+
+#[test]
+fn single_view_with_minimap() {
+    let overlays = Overlays { help: false, minimap: true, gravity: false, physics: false, profiler: false, cursor: None };
+    check("single", overlays, SplitLayout::Single);
+}
+
+#[test]
+fn split_screen_with_gravity_and_physics() {
+    let overlays = Overlays { help: false, minimap: false, gravity: true, physics: true, profiler: false, cursor: None };
+    check("split", overlays, SplitLayout::Horizontal);
+}
+
+#[test]
+fn help() {
+    let overlays = Overlays { help: true, minimap: true, gravity: false, physics: false, profiler: false, cursor: None };
+    check("help", overlays, SplitLayout::Single);
+}